@@ -0,0 +1,188 @@
+use std::f64::consts::PI;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::Dial;
+
+const RADIUS: usize = 10;
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const RESET: &str = "\x1b[0m";
+const POINTER_STYLE: &str = "\x1b[1;32m";
+const ZERO_STYLE: &str = "\x1b[1;33m";
+const STOPPED_STYLE: &str = "\x1b[1;7;31m";
+
+/// Everything shown in a single frame of the animation.
+pub struct Frame<'a> {
+    pub dial: &'a Dial,
+    pub dial_number: usize,
+    /// The rotation number and the rotation just applied, or `None` before the first rotation.
+    pub rotation: Option<(usize, String)>,
+    pub passed_zero: u128,
+}
+
+/// Where position `position` of a dial with `positions` positions sits on the drawing, as (row, column).
+/// Position 0 is at the top and positions increase clockwise. Columns are twice as far apart as rows
+/// so that the dial looks round in a terminal.
+fn cell(position: u16, positions: u16) -> (usize, usize) {
+    let angle = 2.0 * PI * position as f64 / positions as f64;
+    let row = RADIUS as f64 - RADIUS as f64 * angle.cos();
+    let column = 2.0 * RADIUS as f64 + 2.0 * RADIUS as f64 * angle.sin();
+    (row.round() as usize, column.round() as usize)
+}
+
+pub fn render(frame: &Frame) -> String {
+    let dial = frame.dial;
+    let stopped_at_zero = frame.rotation.is_some() && dial.position == 0;
+    let reached_zero = frame.passed_zero > 0;
+
+    let mut grid = vec![vec![" ".to_string(); 4 * RADIUS + 1]; 2 * RADIUS + 1];
+    for position in 0..dial.positions {
+        let (row, column) = cell(position, dial.positions);
+        grid[row][column] = ".".to_string();
+    }
+
+    let (row, column) = cell(0, dial.positions);
+    grid[row][column] = match reached_zero {
+        true => format!("{}0{}", ZERO_STYLE, RESET),
+        false => "0".to_string(),
+    };
+
+    let (row, column) = cell(dial.position, dial.positions);
+    grid[row][column] = match stopped_at_zero {
+        true => format!("{}@{}", STOPPED_STYLE, RESET),
+        false => format!("{}@{}", POINTER_STYLE, RESET),
+    };
+
+    // Show the position the dial points at in the middle of the drawing.
+    let label = dial.position.to_string();
+    let start = 2 * RADIUS + 1 - label.len().div_ceil(2);
+    for (offset, c) in label.chars().enumerate() {
+        grid[RADIUS][start + offset] = c.to_string();
+    }
+
+    let mut output = String::new();
+    for row in grid {
+        output.push_str(row.concat().trim_end());
+        output.push('\n');
+    }
+    output.push('\n');
+
+    match &frame.rotation {
+        Some((number, rotation)) => output.push_str(&format!("Rotation {} turned dial {} by {}.\n", number, frame.dial_number, rotation)),
+        None => output.push_str(&format!("Dial {} starts at {}.\n", frame.dial_number, dial.position)),
+    }
+
+    for password in &dial.passwords {
+        output.push_str(&password.method.describe(password.count));
+        output.push('\n');
+    }
+
+    if stopped_at_zero {
+        output.push_str(&format!("{}The dial stopped at 0!{}\n", STOPPED_STYLE, RESET));
+    } else if reached_zero {
+        output.push_str(&format!("{}The dial passed 0 {} times during this rotation.{}\n", ZERO_STYLE, frame.passed_zero, RESET));
+    }
+
+    output
+}
+
+/// Draws frames to an ANSI terminal, pausing between each one.
+pub struct Animator<W: Write> {
+    writer: W,
+    delay: Duration,
+}
+
+impl<W: Write> Animator<W> {
+    pub fn new(mut writer: W, delay: Duration) -> io::Result<Self> {
+        write!(writer, "{}", HIDE_CURSOR)?;
+        Ok(Self { writer, delay })
+    }
+
+    pub fn show(&mut self, frame: &Frame) -> io::Result<()> {
+        write!(self.writer, "{}{}", CLEAR_SCREEN, render(frame))?;
+        self.writer.flush()?;
+        thread::sleep(self.delay);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        write!(self.writer, "{}", SHOW_CURSOR)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rotation;
+    use crate::password;
+
+    fn strip_ansi(s: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            } else {
+                stripped.push(c);
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn positions_are_placed_clockwise_from_the_top() {
+        assert_eq!(cell(0, 100), (0, 20));
+        assert_eq!(cell(25, 100), (10, 40));
+        assert_eq!(cell(50, 100), (20, 20));
+        assert_eq!(cell(75, 100), (10, 0));
+    }
+
+    #[test]
+    fn frames_show_the_dial_and_counters() {
+        let methods = vec![password::method_by_name("stopped-at-zero").unwrap()];
+        let dial = Dial::with_positions(12, 3).ok().unwrap().with_password_methods(methods);
+        let output = render(&Frame { dial: &dial, dial_number: 1, rotation: Some((4, "R27".to_string())), passed_zero: 0 });
+        let plain = strip_ansi(&output);
+
+        let drawing: String = plain.lines().take(2 * RADIUS + 1).collect();
+        assert_eq!(drawing.matches('.').count(), 10);
+        assert_eq!(drawing.matches('@').count(), 1);
+        assert!(plain.lines().next().unwrap().trim() == "0");
+        assert!(plain.contains("Rotation 4 turned dial 1 by R27."));
+        assert!(plain.contains("The dial stopped at 0 0 times."));
+        assert!(output.contains(&format!("{}@{}", POINTER_STYLE, RESET)));
+        assert!(!output.contains(STOPPED_STYLE));
+    }
+
+    #[test]
+    fn zero_is_highlighted() {
+        let dial = Dial::new(50).rotate(Rotation::Right(150u16)).unwrap();
+        let output = render(&Frame { dial: &dial, dial_number: 1, rotation: Some((1, "R150".to_string())), passed_zero: 2 });
+        assert!(output.contains(&format!("{}@{}", STOPPED_STYLE, RESET)));
+        assert!(strip_ansi(&output).contains("The dial stopped at 0!"));
+
+        let dial = Dial::new(50).rotate(Rotation::Right(160u16)).unwrap();
+        let output = render(&Frame { dial: &dial, dial_number: 1, rotation: Some((1, "R160".to_string())), passed_zero: 2 });
+        assert!(output.contains(&format!("{}0{}", ZERO_STYLE, RESET)));
+        assert!(strip_ansi(&output).contains("The dial passed 0 2 times during this rotation."));
+    }
+
+    #[test]
+    fn frames_are_written_with_ansi_codes() {
+        let dial = Dial::new(50);
+        let mut animator = Animator::new(Vec::new(), Duration::ZERO).unwrap();
+        animator.show(&Frame { dial: &dial, dial_number: 1, rotation: None, passed_zero: 0 }).unwrap();
+
+        let output = String::from_utf8(animator.writer.clone()).unwrap();
+        assert!(output.starts_with(&format!("{}{}", HIDE_CURSOR, CLEAR_SCREEN)));
+        assert!(strip_ansi(&output).contains("Dial 1 starts at 50."));
+    }
+}
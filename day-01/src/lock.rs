@@ -0,0 +1,89 @@
+use crate::{AddressedRotation, Clicks, Dial, DialError};
+
+/// A combination lock made up of several independent dials.
+#[derive(Debug, Clone)]
+pub struct Lock {
+    pub dials: Vec<Dial>,
+    pub times_all_at_zero: u128,
+}
+
+impl Lock {
+    pub fn new(dials: Vec<Dial>) -> Self {
+        Self { dials, times_all_at_zero: 0 }
+    }
+
+    pub fn rotate<T: Clicks>(mut self, instruction: AddressedRotation<T>) -> Result<Self, DialError> {
+        let dials = self.dials.len();
+        let dial = instruction.dial.checked_sub(1)
+            .and_then(|index| self.dials.get_mut(index))
+            .ok_or(DialError::NoSuchDial { dial: instruction.dial, dials })?;
+
+        dial.turn(instruction.rotation)?;
+
+        // If every dial now reads 0, increment the counter.
+        if self.dials.iter().all(|d| d.position == 0) {
+            self.times_all_at_zero = self.times_all_at_zero.checked_add(1)
+                .ok_or(DialError::CounterOverflow)?;
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rotation;
+    use std::str::FromStr;
+
+    fn lock(dials: usize) -> Lock {
+        Lock::new((0..dials).map(|_| Dial::new(50)).collect())
+    }
+
+    #[test]
+    fn each_dial_keeps_its_own_counters() {
+        let mut lock = lock(2);
+        for instruction in ["1:L50", "2:R150", "L100", "2:L1"] {
+            lock = lock.rotate(AddressedRotation::<u16>::from_str(instruction).unwrap()).unwrap();
+        }
+
+        assert_eq!(lock.dials[0].position, 0);
+        assert_eq!(lock.dials[0].password("stopped-at-zero"), Some(2));
+        assert_eq!(lock.dials[0].password("passed-zero"), Some(2));
+        assert_eq!(lock.dials[1].position, 99);
+        assert_eq!(lock.dials[1].password("stopped-at-zero"), Some(1));
+        assert_eq!(lock.dials[1].password("passed-zero"), Some(2));
+    }
+
+    #[test]
+    fn moments_with_every_dial_at_zero_are_counted() {
+        let mut lock = lock(3);
+        for instruction in ["1:L50", "2:R50", "3:L150", "2:R100", "1:L1", "1:R1"] {
+            lock = lock.rotate(AddressedRotation::<u16>::from_str(instruction).unwrap()).unwrap();
+        }
+
+        assert_eq!(lock.times_all_at_zero, 3);
+    }
+
+    #[test]
+    fn a_single_dial_lock_matches_a_dial() {
+        let rotations = [Rotation::Left(68u16), Rotation::Left(30), Rotation::Right(48), Rotation::Left(5), Rotation::Right(60)];
+
+        let mut lock = lock(1);
+        let mut dial = Dial::new(50);
+        for rotation in rotations {
+            lock = lock.rotate(AddressedRotation { dial: 1, rotation }).unwrap();
+            dial = dial.rotate(rotation).unwrap();
+        }
+
+        assert_eq!(lock.dials[0].position, dial.position);
+        assert_eq!(lock.dials[0].passwords.iter().map(|p| p.count).collect::<Vec<u128>>(), dial.passwords.iter().map(|p| p.count).collect::<Vec<u128>>());
+        assert_eq!(Some(lock.times_all_at_zero), dial.password("stopped-at-zero"));
+    }
+
+    #[test]
+    fn missing_dials_are_reported() {
+        let lock = lock(2).rotate(AddressedRotation { dial: 3, rotation: Rotation::Left(1u16) });
+        assert!(lock.is_err_and(|e| e == DialError::NoSuchDial { dial: 3, dials: 2 }));
+    }
+}
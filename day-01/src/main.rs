@@ -1,141 +1,629 @@
+mod animation;
+mod lock;
+mod password;
+mod trace;
+
 use std::env;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+use std::io::{self, BufRead, BufReader, BufWriter};
 use std::path::Path;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::time::Duration;
+
+use animation::{Animator, Frame};
+use lock::Lock;
+use password::{Direction, Movement, Password, PasswordMethod, PasswordsByStart};
+use trace::{TraceFormat, TraceRecord, TraceWriter};
+
+const DEFAULT_POSITIONS: u16 = 100;
+const DEFAULT_STARTING_POSITION: u16 = 50;
+const DEFAULT_FRAME_DELAY: u16 = 100;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    let file_path = &args[1];
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}", message);
+            println!("Usage: day-01 [--dials <count>] [--positions <count>] [--start <position>] [--lenient] [--trace <file>] [--trace-format <csv|jsonl>] [--method <name>]... [--solve <password>] [--animate] [--frame-delay <milliseconds>] [<file>|-]");
+            println!("Password methods: {}", password::METHOD_NAMES.join(", "));
+            return;
+        }
+    };
 
-    println!("Applying instructions from {:?}", file_path);
-    let path = Path::new(file_path);
-    let rotations = Rotation::<u16>::from_file(path.to_str().unwrap());
+    let methods = options.methods.iter()
+        .filter_map(|name| password::method_by_name(name))
+        .collect();
 
-    if rotations.is_err() {
-        println!("Unable to read the file {:?}", file_path);
-        return;
-    }
+    let dials = (0..options.dials)
+        .map(|_| Dial::with_positions(options.positions, options.starting_position))
+        .collect::<Result<Vec<Dial>, DialError>>();
+
+    let mut lock = match dials {
+        Ok(dials) => Lock::new(dials.into_iter().map(|dial| dial.with_password_methods(Vec::clone(&methods))).collect()),
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    type Instructions = Box<dyn Iterator<Item = Result<AddressedRotation<u128>, RotationFileError>>>;
+    let (source, instructions): (String, Instructions) = match &options.file_path {
+        Some(file_path) => {
+            let path = Path::new(file_path);
+            match Rotation::<u128>::from_file(path.to_str().unwrap(), options.parse_mode) {
+                Ok(reader) => (format!("{:?}", file_path), Box::new(reader)),
+                Err(_) => {
+                    println!("Unable to read the file {:?}", file_path);
+                    return;
+                }
+            }
+        },
+        None => ("standard input".to_string(), Box::new(RotationReader::new(io::stdin().lock(), options.parse_mode))),
+    };
+
+    println!("Applying instructions from {}", source);
+
+    let method_names: Vec<&str> = methods.iter().map(|method| method.name()).collect();
+    let mut trace_writer = match &options.trace_path {
+        Some(trace_path) => match File::create(trace_path).and_then(|f| TraceWriter::new(BufWriter::new(f), options.trace_format, &method_names)) {
+            Ok(trace_writer) => Some(trace_writer),
+            Err(_) => {
+                println!("Unable to write the trace to {:?}", trace_path);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let mut animator = match options.animate {
+        true => match Animator::new(io::stdout(), Duration::from_millis(options.frame_delay as u64)) {
+            Ok(mut animator) => {
+                let frame = Frame { dial: &lock.dials[0], dial_number: 1, rotation: None, passed_zero: 0 };
+                if animator.show(&frame).is_err() {
+                    println!("Unable to animate the dial");
+                    return;
+                }
+                Some(animator)
+            },
+            Err(_) => {
+                println!("Unable to animate the dial");
+                return;
+            }
+        },
+        false => None,
+    };
+
+    // Solving counts the passwords for every starting position as the rotations are applied, so
+    // that none of them need to be kept.
+    let mut solvers: Vec<Vec<Option<Box<dyn PasswordsByStart>>>> = match options.solve_target {
+        Some(_) => lock.dials.iter()
+            .map(|dial| dial.passwords.iter().map(|password| password.method.passwords_by_start(dial.positions)).collect())
+            .collect(),
+        None => Vec::new(),
+    };
 
     let mut rotation_count = 0;
-    let mut dial = Dial::new(50);
-    for rotation in rotations.ok().unwrap() {
-        dial = dial.rotate(rotation);
+    for item in instructions {
+        let instruction = match item {
+            Ok(instruction) => instruction,
+            Err(RotationFileError::InvalidLine(invalid_line)) if options.parse_mode == ParseMode::Lenient => {
+                println!("Skipping {}.", invalid_line);
+                continue;
+            },
+            Err(RotationFileError::InvalidLine(invalid_line)) => {
+                println!("Unable to apply the instructions from {}, {}.", source, invalid_line);
+                return;
+            },
+            Err(RotationFileError::Unreadable { line_number }) => {
+                println!("Unable to read line {} of {}", line_number, source);
+                return;
+            },
+            Err(RotationFileError::FileNotFound) => {
+                println!("Unable to read {}", source);
+                return;
+            }
+        };
+
+        let (dial_number, rotation) = (instruction.dial, instruction.rotation);
+        let before = match trace_writer.is_some() || animator.is_some() {
+            true => lock.dials.get(dial_number.wrapping_sub(1)).cloned(),
+            false => None,
+        };
+
+        if let Some(dial_solvers) = solvers.get_mut(dial_number.wrapping_sub(1)) {
+            let (direction, clicks) = rotation.direction_and_clicks();
+            for solver in dial_solvers.iter_mut().flatten() {
+                if let Err(e) = solver.apply(direction, clicks) {
+                    println!("{}", e);
+                    return;
+                }
+            }
+        }
+
+        lock = match lock.rotate(instruction) {
+            Ok(lock) => lock,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
         rotation_count += 1;
+
+        if let (Some(trace_writer), Some(before)) = (trace_writer.as_mut(), &before) {
+            let record = TraceRecord::new(rotation_count, dial_number, &rotation, before, &lock.dials[dial_number - 1]);
+            if trace_writer.write(&record).is_err() {
+                println!("Unable to write the trace for rotation {}", rotation_count);
+                return;
+            }
+        }
+
+        if let (Some(animator), Some(before)) = (animator.as_mut(), &before) {
+            let dial = &lock.dials[dial_number - 1];
+            let frame = Frame {
+                dial,
+                dial_number,
+                rotation: Some((rotation_count, rotation.to_string())),
+                passed_zero: dial.password("passed-zero").zip(before.password("passed-zero"))
+                    .map_or(0, |(after, before)| after - before),
+            };
+            if animator.show(&frame).is_err() {
+                println!("Unable to animate rotation {}", rotation_count);
+                return;
+            }
+        }
+    }
+
+    if let Some(animator) = animator
+        && animator.finish().is_err() {
+        println!("Unable to finish animating the dial");
+        return;
+    }
+
+    if let Some(trace_writer) = trace_writer
+        && trace_writer.finish().is_err() {
+        println!("Unable to finish writing the trace");
+        return;
+    }
+
+    if let Some(target) = options.solve_target {
+        solve(&lock, &solvers, target);
+        return;
     }
 
     println!("The file has {} lines.", rotation_count);
-    println!("The dial stopped at 0 {} times.", dial.times_stopped_at_zero);
-    println!("The dial passed 0 {} times.", dial.times_passed_zero);
+    for (index, dial) in lock.dials.iter().enumerate() {
+        if lock.dials.len() > 1 {
+            println!("Dial {}:", index + 1);
+        }
+
+        for password in &dial.passwords {
+            println!("{}", password.method.describe(password.count));
+        }
+    }
+
+    if lock.dials.len() > 1 {
+        println!("All {} dials read 0 at the same time {} times.", lock.dials.len(), lock.times_all_at_zero);
+    }
+}
+
+fn solve(lock: &Lock, solvers: &[Vec<Option<Box<dyn PasswordsByStart>>>], target: u128) {
+    for (index, (dial, dial_solvers)) in lock.dials.iter().zip(solvers).enumerate() {
+        if lock.dials.len() > 1 {
+            println!("Dial {}:", index + 1);
+        }
+
+        for (password, solver) in dial.passwords.iter().zip(dial_solvers) {
+            let name = password.method.name();
+            match solver.as_deref().map(|solver| password::starting_positions(solver, target)) {
+                Some(Ok(starts)) if starts.is_empty() => println!("No starting position gives a {} password of {}.", name, target),
+                Some(Ok(starts)) => println!(
+                    "Starting at {} gives a {} password of {}.",
+                    starts.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(", "),
+                    name,
+                    target
+                ),
+                Some(Err(e)) => println!("{}", e),
+                None => println!("Starting positions cannot be solved for the {} password method.", name),
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
+struct Options {
+    /// Where to read rotations from, or `None` for standard input.
+    file_path: Option<String>,
+    dials: usize,
+    positions: u16,
+    starting_position: u16,
+    parse_mode: ParseMode,
+    trace_path: Option<String>,
+    trace_format: TraceFormat,
+    methods: Vec<String>,
+    solve_target: Option<u128>,
+    animate: bool,
+    frame_delay: u16,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut file_path = None;
+        let mut dials = 1;
+        let mut positions = DEFAULT_POSITIONS;
+        let mut starting_position = DEFAULT_STARTING_POSITION;
+        let mut parse_mode = ParseMode::Strict;
+        let mut trace_path = None;
+        let mut trace_format = TraceFormat::Csv;
+        let mut methods = Vec::new();
+        let mut solve_target = None;
+        let mut animate = false;
+        let mut frame_delay = DEFAULT_FRAME_DELAY;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dials" => match Self::parse_value(arg, args.next())? {
+                    0 => return Err("The option --dials needs at least one dial.".to_string()),
+                    count => dials = count as usize,
+                },
+                "--positions" => positions = Self::parse_value(arg, args.next())?,
+                "--start" => starting_position = Self::parse_value(arg, args.next())?,
+                "--lenient" => parse_mode = ParseMode::Lenient,
+                "--trace" => trace_path = Some(Self::required_value(arg, args.next())?.clone()),
+                "--trace-format" => trace_format = TraceFormat::from_str(Self::required_value(arg, args.next())?)?,
+                "--method" => {
+                    let name = Self::required_value(arg, args.next())?;
+                    if password::method_by_name(name).is_none() {
+                        return Err(format!("Unknown password method {:?}, expected one of {}.", name, password::METHOD_NAMES.join(", ")));
+                    }
+                    methods.push(name.clone());
+                },
+                "--solve" => {
+                    let target = Self::required_value(arg, args.next())?;
+                    solve_target = Some(target.parse::<u128>()
+                        .map_err(|_| format!("The option {} expects a whole number, not {:?}.", arg, target))?);
+                },
+                "--animate" => animate = true,
+                "--frame-delay" => frame_delay = Self::parse_value(arg, args.next())?,
+                flag if flag.starts_with("--") => return Err(format!("Unknown option {:?}.", flag)),
+                path if file_path.is_none() => file_path = Some(path.to_string()),
+                extra => return Err(format!("Unexpected argument {:?}.", extra)),
+            }
+        }
+
+        if methods.is_empty() {
+            methods = password::METHOD_NAMES.iter().map(|name| name.to_string()).collect();
+        }
+
+        // A path of "-" reads from standard input, just like giving no path at all.
+        let file_path = file_path.filter(|path| path != "-");
+
+        Ok(Self { file_path, dials, positions, starting_position, parse_mode, trace_path, trace_format, methods, solve_target, animate, frame_delay })
+    }
+
+    fn required_value<'a>(flag: &str, value: Option<&'a String>) -> Result<&'a String, String> {
+        value.ok_or_else(|| format!("The option {} requires a value.", flag))
+    }
+
+    fn parse_value(flag: &str, value: Option<&String>) -> Result<u16, String> {
+        let value = Self::required_value(flag, value)?;
+        value.parse::<u16>()
+            .map_err(|_| format!("The option {} expects a whole number between 0 and {}, not {:?}.", flag, u16::MAX, value))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Rotation<T> {
     Left(T),
     Right(T)
 }
 
-struct FileNotFoundError;
-struct ParseRotationError;
+/// A rotation of one of the dials on a lock, written as `2:L68` to turn the second dial.
+/// Without a dial number, the first dial is turned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AddressedRotation<T> {
+    dial: usize,
+    rotation: Rotation<T>,
+}
+
+/// An unsigned integer type that can hold the number of clicks in a single rotation.
+trait Clicks: FromStr + Copy + fmt::Display + Into<u128> {}
+
+impl Clicks for u8 {}
+impl Clicks for u16 {}
+impl Clicks for u32 {}
+impl Clicks for u64 {}
+impl Clicks for u128 {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParseMode {
+    /// Fail on the first line that is not a rotation.
+    Strict,
+    /// Skip lines that are not rotations, collecting them for reporting.
+    Lenient,
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseRotationError {
+    Empty,
+    UnknownDirection(char),
+    MissingSteps,
+    InvalidSteps,
+    StepsOutOfRange,
+    InvalidDial,
+}
+
+impl fmt::Display for ParseRotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRotationError::Empty => write!(f, "the rotation is empty"),
+            ParseRotationError::UnknownDirection(direction) => write!(f, "unknown direction {:?}, expected 'L' or 'R'", direction),
+            ParseRotationError::MissingSteps => write!(f, "the step count is missing"),
+            ParseRotationError::InvalidSteps => write!(f, "the step count is not a whole number"),
+            ParseRotationError::StepsOutOfRange => write!(f, "the step count is out of range"),
+            ParseRotationError::InvalidDial => write!(f, "the dial number must be a whole number from 1 upwards"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct InvalidLine {
+    line_number: usize,
+    text: String,
+    reason: ParseRotationError,
+}
+
+impl fmt::Display for InvalidLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {:?} is not a valid rotation ({})", self.line_number, self.text, self.reason)
+    }
+}
+
+#[derive(Debug)]
+enum RotationFileError {
+    FileNotFound,
+    Unreadable { line_number: usize },
+    InvalidLine(InvalidLine),
+}
+
+#[derive(Debug, PartialEq)]
+enum DialError {
+    NoPositions,
+    StartingPositionOutOfRange { starting_position: u16, positions: u16 },
+    CounterOverflow,
+    NoSuchDial { dial: usize, dials: usize },
+}
+
+impl fmt::Display for DialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialError::NoPositions => write!(f, "A dial must have at least one position."),
+            DialError::StartingPositionOutOfRange { starting_position, positions } => write!(
+                f,
+                "The starting position {} is not on a dial with {} positions (expected 0 to {}).",
+                starting_position,
+                positions,
+                positions - 1
+            ),
+            DialError::CounterOverflow => write!(f, "The dial reached 0 more times than can be counted."),
+            DialError::NoSuchDial { dial, dials } => write!(f, "There is no dial {} on a lock with {} dials.", dial, dials),
+        }
+    }
+}
 
-impl FromStr for Rotation<u16> {
+impl<T: Clicks> FromStr for Rotation<T> {
     type Err = ParseRotationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let direction = s.chars().next();
-        let steps = &s[1..].parse::<u16>();
+        let mut chars = s.chars();
+        let direction = chars.next().ok_or(ParseRotationError::Empty)?;
+        let steps = chars.as_str();
 
-        match (direction, steps) {
-            (Some('L'), Ok(value)) => Ok(Rotation::Left(*value)),
-            (Some('R'), Ok(value)) => Ok(Rotation::Right(*value)),
-            _ => Err(ParseRotationError)
+        if direction != 'L' && direction != 'R' {
+            return Err(ParseRotationError::UnknownDirection(direction));
+        }
+
+        if steps.is_empty() {
+            return Err(ParseRotationError::MissingSteps);
+        }
+
+        // Only plain digits are accepted, so signs such as "R-5" or "R+5" are rejected.
+        if !steps.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseRotationError::InvalidSteps);
+        }
+
+        let value = steps.parse::<T>().map_err(|_| ParseRotationError::StepsOutOfRange)?;
+
+        match direction {
+            'L' => Ok(Rotation::Left(value)),
+            _ => Ok(Rotation::Right(value)),
         }
     }
 }
 
-impl Rotation<u16> {
-    fn from_file(file_path: &str) -> Result<Vec<Self>, FileNotFoundError> {
+impl<T: Clicks> FromStr for AddressedRotation<T> {
+    type Err = ParseRotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (dial, rotation) = match s.split_once(':') {
+            Some((dial, rotation)) => {
+                let dial = match dial.bytes().all(|b| b.is_ascii_digit()) {
+                    true => dial.parse::<usize>().ok().filter(|&d| d > 0),
+                    false => None,
+                };
+                (dial.ok_or(ParseRotationError::InvalidDial)?, rotation)
+            },
+            None => (1, s),
+        };
+
+        Ok(Self { dial, rotation: Rotation::from_str(rotation)? })
+    }
+}
+
+impl<T: Clicks> fmt::Display for Rotation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rotation::Left(value) => write!(f, "L{}", value),
+            Rotation::Right(value) => write!(f, "R{}", value),
+        }
+    }
+}
+
+impl<T: Clicks> Rotation<T> {
+    fn direction_and_clicks(&self) -> (Direction, u128) {
+        match *self {
+            Rotation::Left(value) => (Direction::Left, value.into()),
+            Rotation::Right(value) => (Direction::Right, value.into()),
+        }
+    }
+
+    fn from_file(file_path: &str, mode: ParseMode) -> Result<RotationReader<BufReader<File>, T>, RotationFileError> {
         let file = File::open(file_path);
         if file.is_err() {
-            return Err(FileNotFoundError);
+            return Err(RotationFileError::FileNotFound);
         }
 
-        let reader = BufReader::new(file.unwrap());
-
-        let mut rotations = Vec::new();
-        for line in reader.lines() {
-            match line {
-                Ok(l) => {
-                    // Strip BOM if present
-                    let clean = l.trim_start_matches('\u{feff}');
-                    match Rotation::<u16>::from_str(clean) {
-                        Ok(r) => rotations.push(r),
-                        Err(_) => continue,
-                    }
+        Ok(RotationReader::new(BufReader::new(file.unwrap()), mode))
+    }
+}
+
+/// Parses rotations one line at a time, so that they can be applied without holding the whole input.
+///
+/// In strict mode nothing more is read after the first invalid line, while in lenient mode every
+/// invalid line is reported and reading carries on.
+struct RotationReader<R, T> {
+    reader: R,
+    mode: ParseMode,
+    buffer: Vec<u8>,
+    line_number: usize,
+    finished: bool,
+    clicks: PhantomData<T>,
+}
+
+impl<R: BufRead, T: Clicks> RotationReader<R, T> {
+    fn new(reader: R, mode: ParseMode) -> Self {
+        Self { reader, mode, buffer: Vec::new(), line_number: 0, finished: false, clicks: PhantomData }
+    }
+}
+
+impl<R: BufRead, T: Clicks> Iterator for RotationReader<R, T> {
+    type Item = Result<AddressedRotation<T>, RotationFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            self.buffer.clear();
+            self.line_number += 1;
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(_) => {
+                    self.finished = true;
+                    return Some(Err(RotationFileError::Unreadable { line_number: self.line_number }));
                 },
-                Err(_) => continue,
             }
+
+            // Invalid UTF-8 is kept (lossily) so it can be reported rather than silently dropped.
+            let line = String::from_utf8_lossy(&self.buffer);
+            let mut clean = line.strip_suffix('\n').unwrap_or(&line);
+            clean = clean.strip_suffix('\r').unwrap_or(clean);
+
+            // Strip BOM if present
+            if self.line_number == 1 {
+                clean = clean.trim_start_matches('\u{feff}');
+            }
+
+            if clean.trim().is_empty() {
+                continue;
+            }
+
+            return match AddressedRotation::<T>::from_str(clean) {
+                Ok(r) => Some(Ok(r)),
+                Err(reason) => {
+                    self.finished = self.mode == ParseMode::Strict;
+                    let invalid_line = InvalidLine { line_number: self.line_number, text: clean.to_string(), reason };
+                    Some(Err(RotationFileError::InvalidLine(invalid_line)))
+                }
+            };
         }
 
-        Ok(rotations)
+        None
     }
 }
 
+#[derive(Debug, Clone)]
 struct Dial {
+    positions: u16,
     position: u16,
-    times_stopped_at_zero: u16,
-    times_passed_zero: u16,
+    passwords: Vec<Password>,
 }
 
 impl Dial {
+    // A standard 100-position dial, as used by the puzzle examples.
+    #[cfg(test)]
     fn new(initial_position: u16) -> Self {
-        Self {
-            position: initial_position,
-            times_stopped_at_zero: 0,
-            times_passed_zero: 0,
-        }
+        Self::with_positions(DEFAULT_POSITIONS, initial_position)
+            .expect("The initial position must be between 0 and 99 (inclusive).")
     }
 
-    fn rotate(mut self, rotation: Rotation<u16>) -> Self {
-        let mut new_position = self.position as i32;
-        match rotation {
-            Rotation::Left(value) => {
-                for _ in 0..value {
-                    new_position = new_position - 1;
-                    if new_position == 0 {
-                        self.times_passed_zero += 1;
-                    } else if new_position < 0 {
-                        new_position = 99;
-                    }
-                }
-            },
-            Rotation::Right(value) => {
-                for _ in 0..value {
-                    new_position = new_position + 1;
-                    if new_position > 99 {
-                        new_position = 0;
-                    }
+    fn with_positions(positions: u16, initial_position: u16) -> Result<Self, DialError> {
+        if positions == 0 {
+            return Err(DialError::NoPositions);
+        }
 
-                    if new_position == 0 {
-                        self.times_passed_zero += 1;
-                    }
-                }
-            }
+        if initial_position >= positions {
+            return Err(DialError::StartingPositionOutOfRange { starting_position: initial_position, positions });
         }
-        assert!(new_position >= 0 && new_position < 100, "The new position must be between 0 and 99 (inclusive).");
-        self.position = new_position as u16;
 
-        assert!(self.position < 100);
+        // Count every password method until the dial is given its own.
+        let methods = password::METHOD_NAMES.iter().filter_map(|name| password::method_by_name(name)).collect();
 
-        // If the dial has stopped at 0, increment the counter.
-        if self.position == 0 {
-            self.times_stopped_at_zero += 1;
-        }
+        Ok(Self {
+            positions,
+            position: initial_position,
+            passwords: Vec::new(),
+        }.with_password_methods(methods))
+    }
 
-        // Return the updated dial.
+    fn with_password_methods(mut self, methods: Vec<Rc<dyn PasswordMethod>>) -> Self {
+        self.passwords = methods.into_iter().map(Password::new).collect();
         self
     }
+
+    /// The password counted so far by the method called `name`, or `None` if the dial does not count it.
+    fn password(&self, name: &str) -> Option<u128> {
+        self.passwords.iter()
+            .find(|password| password.method.name() == name)
+            .map(|password| password.count)
+    }
+
+    // The consuming form of `turn`, convenient for following a sequence of rotations on a lone dial.
+    #[cfg(test)]
+    fn rotate<T: Clicks>(mut self, rotation: Rotation<T>) -> Result<Self, DialError> {
+        self.turn(rotation)?;
+
+        // Return the updated dial.
+        Ok(self)
+    }
+
+    fn turn<T: Clicks>(&mut self, rotation: Rotation<T>) -> Result<(), DialError> {
+        let (direction, clicks) = rotation.direction_and_clicks();
+        let movement = Movement::new(self.positions, self.position, direction, clicks);
+
+        self.position = movement.to;
+
+        // Report the movement to each of the password methods.
+        for password in self.passwords.iter_mut() {
+            password.count = password.count.checked_add(password.method.count(&movement))
+                .ok_or(DialError::CounterOverflow)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -145,60 +633,164 @@ mod tests {
     #[test]
     fn rotate_right_works() {
         let dial = Dial::new(0);
-        let rotated = dial.rotate(Rotation::Right(3));
+        let rotated = dial.rotate(Rotation::Right(3u16)).unwrap();
         assert_eq!(rotated.position, 3);
-        assert_eq!(rotated.times_stopped_at_zero, 0);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(0));
 
         let dial = Dial::new(50);
-        let rotated = dial.rotate(Rotation::Right(49));
+        let rotated = dial.rotate(Rotation::Right(49u16)).unwrap();
         assert_eq!(rotated.position, 99);
-        assert_eq!(rotated.times_stopped_at_zero, 0);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(0));
 
         let dial = Dial::new(99);
-        let rotated = dial.rotate(Rotation::Right(1));
+        let rotated = dial.rotate(Rotation::Right(1u16)).unwrap();
         assert_eq!(rotated.position, 0);
-        assert_eq!(rotated.times_stopped_at_zero, 1);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(1));
 
         let dial = Dial::new(0);
-        let rotated = dial.rotate(Rotation::Right(110));
+        let rotated = dial.rotate(Rotation::Right(110u16)).unwrap();
         assert_eq!(rotated.position, 10);
-        assert_eq!(rotated.times_stopped_at_zero, 0);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(0));
     }
 
     #[test]
     fn rotate_left_works() {
         let dial = Dial::new(0);
-        let rotated = dial.rotate(Rotation::Left(3));
+        let rotated = dial.rotate(Rotation::Left(3u16)).unwrap();
         assert_eq!(rotated.position, 97);
-        assert_eq!(rotated.times_stopped_at_zero, 0);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(0));
 
         let dial = Dial::new(50);
-        let rotated = dial.rotate(Rotation::Left(49));
+        let rotated = dial.rotate(Rotation::Left(49u16)).unwrap();
         assert_eq!(rotated.position, 1);
-        assert_eq!(rotated.times_stopped_at_zero, 0);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(0));
 
         let dial = Dial::new(1);
-        let rotated = dial.rotate(Rotation::Left(1));
+        let rotated = dial.rotate(Rotation::Left(1u16)).unwrap();
         assert_eq!(rotated.position, 0);
-        assert_eq!(rotated.times_stopped_at_zero, 1);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(1));
 
         let dial = Dial::new(0);
-        let rotated = dial.rotate(Rotation::Left(110));
+        let rotated = dial.rotate(Rotation::Left(110u16)).unwrap();
         assert_eq!(rotated.position, 90);
-        assert_eq!(rotated.times_stopped_at_zero, 0);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(0));
+    }
+
+    #[test]
+    fn rotate_matches_click_by_click_simulation() {
+        fn simulate(position: u16, rotation: &Rotation<u16>) -> (u16, u128) {
+            let mut position = position as i32;
+            let mut passes = 0u128;
+            let (step, clicks) = match rotation {
+                Rotation::Left(value) => (-1, *value),
+                Rotation::Right(value) => (1, *value),
+            };
+            for _ in 0..clicks {
+                position = (position + step).rem_euclid(100);
+                if position == 0 {
+                    passes += 1;
+                }
+            }
+            (position as u16, passes)
+        }
+
+        for start in 0..100 {
+            for clicks in [0, 1, 2, 49, 50, 99, 100, 101, 199, 200, 250, 795, 1000] {
+                for rotation in [Rotation::Left(clicks), Rotation::Right(clicks)] {
+                    let (expected_position, expected_passes) = simulate(start, &rotation);
+                    let rotated = Dial::new(start).rotate(rotation).unwrap();
+                    assert_eq!(rotated.position, expected_position);
+                    assert_eq!(rotated.password("passed-zero"), Some(expected_passes));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dial_size_can_be_configured() {
+        let dial = Dial::with_positions(60, 59).ok().unwrap();
+        let rotated = dial.rotate(Rotation::Right(1u16)).unwrap();
+        assert_eq!(rotated.position, 0);
+        assert_eq!(rotated.password("stopped-at-zero"), Some(1));
+
+        let dial = Dial::with_positions(360, 10).ok().unwrap();
+        let rotated = dial.rotate(Rotation::Left(740u16)).unwrap();
+        assert_eq!(rotated.position, 350);
+        assert_eq!(rotated.password("passed-zero"), Some(3));
+
+        let dial = Dial::with_positions(1000, 999).ok().unwrap();
+        let rotated = dial.rotate(Rotation::Right(2001u16)).unwrap();
+        assert_eq!(rotated.position, 0);
+        assert_eq!(rotated.password("passed-zero"), Some(3));
+        assert_eq!(rotated.password("stopped-at-zero"), Some(1));
+    }
+
+    #[test]
+    fn invalid_dials_are_rejected() {
+        let dial = Dial::with_positions(0, 0);
+        assert!(dial.is_err_and(|e| e == DialError::NoPositions));
+
+        let dial = Dial::with_positions(60, 60);
+        assert!(dial.is_err_and(|e| e == DialError::StartingPositionOutOfRange { starting_position: 60, positions: 60 }));
+    }
+
+    #[test]
+    fn options_can_be_parsed() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<String>>();
+
+        let options = Options::parse(&args("data/example.txt"));
+        assert_eq!(options, Ok(Options {
+            file_path: Some("data/example.txt".to_string()),
+            dials: 1,
+            positions: 100,
+            starting_position: 50,
+            parse_mode: ParseMode::Strict,
+            trace_path: None,
+            trace_format: TraceFormat::Csv,
+            methods: vec!["stopped-at-zero".to_string(), "passed-zero".to_string()],
+            solve_target: None,
+            animate: false,
+            frame_delay: 100,
+        }));
+
+        let options = Options::parse(&args("--positions 360 data/example.txt --start 0 --lenient --trace trace.jsonl --trace-format jsonl --method 0x434C49434B --solve 3 --dials 2 --animate --frame-delay 250"));
+        assert_eq!(options, Ok(Options {
+            file_path: Some("data/example.txt".to_string()),
+            dials: 2,
+            positions: 360,
+            starting_position: 0,
+            parse_mode: ParseMode::Lenient,
+            trace_path: Some("trace.jsonl".to_string()),
+            trace_format: TraceFormat::JsonLines,
+            methods: vec!["0x434C49434B".to_string()],
+            solve_target: Some(3),
+            animate: true,
+            frame_delay: 250,
+        }));
+
+        assert!(Options::parse(&args("")).is_ok_and(|o| o.file_path.is_none()));
+        assert!(Options::parse(&args("- --dials 2")).is_ok_and(|o| o.file_path.is_none()));
+        assert!(Options::parse(&args("data/example.txt --start")).is_err());
+        assert!(Options::parse(&args("data/example.txt --start -1")).is_err());
+        assert!(Options::parse(&args("data/example.txt --size 10")).is_err());
+        assert!(Options::parse(&args("data/example.txt data/other.txt")).is_err());
+        assert!(Options::parse(&args("data/example.txt --trace-format xml")).is_err());
+        assert!(Options::parse(&args("data/example.txt --method clockwise")).is_err());
+        assert!(Options::parse(&args("data/example.txt --solve many")).is_err());
+        assert!(Options::parse(&args("data/example.txt --dials 0")).is_err());
     }
 
     #[test]
     fn odd_example_works() {
-        let sequence = [Rotation::Left(795)];
+        let sequence = [Rotation::Left(795u16)];
 
         let mut dial = Dial::new(95);
 
         for rotation in sequence {
-            dial = dial.rotate(rotation);
+            dial = dial.rotate(rotation).unwrap();
         }
 
-        assert_eq!(dial.times_stopped_at_zero, 1);
+        assert_eq!(dial.password("stopped-at-zero"), Some(1));
     }
 
     #[test]
@@ -219,31 +811,117 @@ mod tests {
         let mut dial = Dial::new(50);
 
         for rotation in sequence {
-            dial = dial.rotate(rotation);
+            dial = dial.rotate(rotation).unwrap();
         }
 
-        assert_eq!(dial.times_stopped_at_zero, 3);
-        assert_eq!(dial.times_passed_zero, 6);
+        assert_eq!(dial.password("stopped-at-zero"), Some(3));
+        assert_eq!(dial.password("passed-zero"), Some(6));
+    }
+
+    #[test]
+    fn dial_reports_to_password_methods() {
+        let methods = vec![password::method_by_name("passed-zero").unwrap(), password::method_by_name("stopped-at-zero").unwrap()];
+        let mut dial = Dial::new(50).with_password_methods(methods);
+
+        for rotation in [Rotation::Left(68u16), Rotation::Left(30), Rotation::Right(48), Rotation::Left(5), Rotation::Right(60)] {
+            dial = dial.rotate(rotation).unwrap();
+        }
+
+        let passwords: Vec<(&str, u128)> = dial.passwords.iter().map(|p| (p.method.name(), p.count)).collect();
+        assert_eq!(passwords, vec![("passed-zero", 3), ("stopped-at-zero", 1)]);
     }
 
     #[test]
     fn rotation_can_be_parsed()
     {
         let input = "L23";
-        let rotation = Rotation::from_str(input);
-        assert!(rotation.is_ok_and(|r| r == Rotation::Left(23)));
+        let rotation = Rotation::<u16>::from_str(input);
+        assert!(rotation.is_ok_and(|r| r == Rotation::Left(23u16)));
 
         let input = "R78";
-        let rotation = Rotation::from_str(input);
-        assert!(rotation.is_ok_and(|r| r == Rotation::Right(78)));
+        let rotation = Rotation::<u16>::from_str(input);
+        assert!(rotation.is_ok_and(|r| r == Rotation::Right(78u16)));
 
         let input = "R65536";
-        let rotation = Rotation::from_str(input);
-        assert!(rotation.is_err_and(|e| matches!(e, ParseRotationError)));
+        let rotation = Rotation::<u16>::from_str(input);
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::StepsOutOfRange));
 
         let input = "Z12";
-        let rotation = Rotation::from_str(input);
-        assert!(rotation.is_err_and(|e| matches!(e, ParseRotationError)));
+        let rotation = Rotation::<u16>::from_str(input);
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::UnknownDirection('Z')));
+
+        let input = "R-5";
+        let rotation = Rotation::<u16>::from_str(input);
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::InvalidSteps));
+
+        let input = "R+5";
+        let rotation = Rotation::<u16>::from_str(input);
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::InvalidSteps));
+
+        let input = "L";
+        let rotation = Rotation::<u16>::from_str(input);
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::MissingSteps));
+    }
+
+    #[test]
+    fn addressed_rotation_can_be_parsed()
+    {
+        let rotation = AddressedRotation::<u16>::from_str("2:L68");
+        assert!(rotation.is_ok_and(|r| r == AddressedRotation { dial: 2, rotation: Rotation::Left(68) }));
+
+        let rotation = AddressedRotation::<u16>::from_str("R14");
+        assert!(rotation.is_ok_and(|r| r == AddressedRotation { dial: 1, rotation: Rotation::Right(14) }));
+
+        let rotation = AddressedRotation::<u16>::from_str("0:R14");
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::InvalidDial));
+
+        let rotation = AddressedRotation::<u16>::from_str("x:R14");
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::InvalidDial));
+
+        let rotation = AddressedRotation::<u16>::from_str("3:Z14");
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::UnknownDirection('Z')));
+    }
+
+    #[test]
+    fn rotation_can_be_parsed_into_wider_types()
+    {
+        let rotation = Rotation::<u32>::from_str("R65536");
+        assert!(rotation.is_ok_and(|r| r == Rotation::Right(65536)));
+
+        let rotation = Rotation::<u64>::from_str("L4294967296");
+        assert!(rotation.is_ok_and(|r| r == Rotation::Left(4294967296)));
+
+        let rotation = Rotation::<u128>::from_str("R340282366920938463463374607431768211455");
+        assert!(rotation.is_ok_and(|r| r == Rotation::Right(u128::MAX)));
+
+        let rotation = Rotation::<u128>::from_str("R340282366920938463463374607431768211456");
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::StepsOutOfRange));
+
+        let rotation = Rotation::<u64>::from_str("");
+        assert!(rotation.is_err_and(|e| e == ParseRotationError::Empty));
+    }
+
+    #[test]
+    fn long_rotations_are_counted_without_wrapping() {
+        let dial = Dial::new(50);
+        let rotated = dial.rotate(Rotation::Right(u64::MAX)).unwrap();
+        assert_eq!(rotated.password("passed-zero"), Some((50 + u64::MAX as u128) / 100));
+        assert_eq!(rotated.position, ((50 + u64::MAX as u128) % 100) as u16);
+
+        let dial = Dial::new(50);
+        let rotated = dial.rotate(Rotation::Left(u128::MAX)).unwrap();
+        assert_eq!(rotated.password("passed-zero"), Some(u128::MAX / 100 + 1));
+        assert_eq!(rotated.position, 95);
+    }
+
+    #[test]
+    fn counter_overflow_is_reported() {
+        let dial = Dial::with_positions(1, 0).ok().unwrap();
+        let rotated = dial.rotate(Rotation::Right(u128::MAX)).unwrap();
+        assert_eq!(rotated.password("passed-zero"), Some(u128::MAX));
+
+        let rotated = rotated.rotate(Rotation::Right(1u8));
+        assert!(rotated.is_err_and(|e| e == DialError::CounterOverflow));
     }
 
     #[test]
@@ -251,8 +929,71 @@ mod tests {
     {
         println!("{:?}", env::current_dir().unwrap());
         let path = Path::new("./data/example.txt");
-        let rotations = Rotation::<u16>::from_file(path.to_str().unwrap());
+        let rotations = Rotation::<u128>::from_file(path.to_str().unwrap(), ParseMode::Strict);
         assert!(rotations.is_ok());
+        let rotations = rotations.ok().unwrap().collect::<Result<Vec<AddressedRotation<u128>>, RotationFileError>>();
         assert!(rotations.is_ok_and(|r| r.len() == 10));
     }
+
+    #[test]
+    fn strict_parsing_stops_at_the_first_invalid_line()
+    {
+        let path = Path::new("./data/malformed.txt");
+        let mut rotations = Rotation::<u16>::from_file(path.to_str().unwrap(), ParseMode::Strict).ok().unwrap();
+        assert!(rotations.next().is_some_and(|r| r.is_ok_and(|r| r.rotation == Rotation::Left(68))));
+        assert!(rotations.next().is_some_and(|r| r.is_ok_and(|r| r.rotation == Rotation::Left(30))));
+
+        let expected = InvalidLine { line_number: 4, text: "X12".to_string(), reason: ParseRotationError::UnknownDirection('X') };
+        assert!(rotations.next().is_some_and(|r| r.is_err_and(|e| matches!(e, RotationFileError::InvalidLine(line) if line == expected))));
+        assert!(rotations.next().is_none());
+    }
+
+    #[test]
+    fn lenient_parsing_collects_every_invalid_line()
+    {
+        let path = Path::new("./data/malformed.txt");
+        let parsed = Rotation::<u16>::from_file(path.to_str().unwrap(), ParseMode::Lenient).ok().unwrap();
+        let (rotations, invalid_lines): (Vec<_>, Vec<_>) = parsed.partition(|r| r.is_ok());
+
+        let rotations: Vec<Rotation<u16>> = rotations.into_iter().map(|r| r.unwrap().rotation).collect();
+        assert_eq!(rotations, vec![Rotation::Left(68), Rotation::Left(30), Rotation::Right(48), Rotation::Left(5)]);
+
+        let invalid_lines: Vec<InvalidLine> = invalid_lines.into_iter()
+            .map(|r| match r {
+                Err(RotationFileError::InvalidLine(line)) => line,
+                other => panic!("Unexpected {:?}", other),
+            })
+            .collect();
+        let invalid_lines: Vec<(usize, &str, &ParseRotationError)> = invalid_lines.iter()
+            .map(|l| (l.line_number, l.text.as_str(), &l.reason))
+            .collect();
+        assert_eq!(invalid_lines, vec![
+            (4, "X12", &ParseRotationError::UnknownDirection('X')),
+            (7, "R-5", &ParseRotationError::InvalidSteps),
+            (8, "L", &ParseRotationError::MissingSteps),
+            (9, "R99999999999999999999999999999999999999999", &ParseRotationError::StepsOutOfRange),
+        ]);
+    }
+
+    #[test]
+    fn missing_file_is_reported()
+    {
+        let rotations = Rotation::<u16>::from_file("./data/missing.txt", ParseMode::Lenient);
+        assert!(rotations.is_err_and(|e| matches!(e, RotationFileError::FileNotFound)));
+    }
+
+    #[test]
+    fn rotations_can_be_streamed_from_any_reader()
+    {
+        let input = "L68\n2:R48\n\nL5".as_bytes();
+        let rotations: Vec<AddressedRotation<u16>> = RotationReader::new(input, ParseMode::Strict)
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(rotations, vec![
+            AddressedRotation { dial: 1, rotation: Rotation::Left(68) },
+            AddressedRotation { dial: 2, rotation: Rotation::Right(48) },
+            AddressedRotation { dial: 1, rotation: Rotation::Left(5) },
+        ]);
+    }
 }
\ No newline at end of file
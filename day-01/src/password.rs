@@ -0,0 +1,394 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::DialError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+/// A single rotation of the dial, from where it started to where it came to rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Movement {
+    pub positions: u16,
+    pub from: u16,
+    pub to: u16,
+    pub direction: Direction,
+    pub clicks: u128,
+}
+
+impl Movement {
+    pub fn new(positions: u16, from: u16, direction: Direction, clicks: u128) -> Self {
+        let (p, f) = (positions as u128, from as u128);
+        let partial_turn = clicks % p;
+        let to = match direction {
+            Direction::Left => (f + p - partial_turn) % p,
+            Direction::Right => (f + partial_turn) % p,
+        };
+
+        Self { positions, from, to: to as u16, direction, clicks }
+    }
+
+    /// How many clicks of this movement left the dial pointing at `target`.
+    pub fn times_reached(&self, target: u16) -> u128 {
+        let p = self.positions as u128;
+        let (from, target) = (self.from as u128, target as u128);
+
+        // Clicks until the first time the target is reached, a whole turn if the dial is already there.
+        let first = match self.direction {
+            Direction::Left => (from + p - target) % p,
+            Direction::Right => (target + p - from) % p,
+        };
+        let first = if first == 0 { p } else { first };
+
+        if self.clicks < first {
+            0
+        } else {
+            1 + (self.clicks - first) / p
+        }
+    }
+}
+
+/// A rule for deriving a password from the way the dial moves.
+pub trait PasswordMethod {
+    fn name(&self) -> &'static str;
+
+    /// How much a single movement of the dial adds to the password.
+    fn count(&self, movement: &Movement) -> u128;
+
+    fn describe(&self, password: u128) -> String {
+        format!("The {} password is {}.", self.name(), password)
+    }
+
+    /// Counts the password for every starting position of a dial with `positions` positions at once,
+    /// or `None` if the method has no way to work this out in a single pass.
+    fn passwords_by_start(&self, _positions: u16) -> Option<Box<dyn PasswordsByStart>> {
+        None
+    }
+}
+
+/// The password for every starting position of a dial, counted one movement at a time so that the
+/// movements never need to be kept. A password too large to count is an error rather than a wrong count.
+pub trait PasswordsByStart {
+    fn apply(&mut self, direction: Direction, clicks: u128) -> Result<(), DialError>;
+
+    /// The passwords so far, indexed by starting position.
+    fn passwords(&self) -> Result<Vec<u128>, DialError>;
+}
+
+/// Steps through movements while tracking how far the dial has turned from wherever it started,
+/// so that a position relative to the start can be turned into the start that puts the dial there.
+struct Offset {
+    positions: u128,
+    net: u128,
+}
+
+impl Offset {
+    fn new(positions: u16) -> Self {
+        Self { positions: positions as u128, net: 0 }
+    }
+
+    fn apply(&mut self, direction: Direction, clicks: u128) {
+        let partial_turn = clicks % self.positions;
+        self.net = match direction {
+            Direction::Left => (self.net + self.positions - partial_turn) % self.positions,
+            Direction::Right => (self.net + partial_turn) % self.positions,
+        };
+    }
+
+    /// The starting position that leaves the dial at `position` at this point in the sequence.
+    fn start_for(&self, position: u128) -> usize {
+        ((position + self.positions - self.net) % self.positions) as usize
+    }
+}
+
+/// The number of times the dial is left pointing at 0 after a rotation.
+pub struct StoppedAtZero;
+
+impl PasswordMethod for StoppedAtZero {
+    fn name(&self) -> &'static str {
+        "stopped-at-zero"
+    }
+
+    fn count(&self, movement: &Movement) -> u128 {
+        if movement.to == 0 { 1 } else { 0 }
+    }
+
+    fn describe(&self, password: u128) -> String {
+        format!("The dial stopped at 0 {} times.", password)
+    }
+
+    fn passwords_by_start(&self, positions: u16) -> Option<Box<dyn PasswordsByStart>> {
+        Some(Box::new(StoppedAtZeroByStart { passwords: vec![0; positions as usize], offset: Offset::new(positions) }))
+    }
+}
+
+struct StoppedAtZeroByStart {
+    passwords: Vec<u128>,
+    offset: Offset,
+}
+
+impl PasswordsByStart for StoppedAtZeroByStart {
+    fn apply(&mut self, direction: Direction, clicks: u128) -> Result<(), DialError> {
+        self.offset.apply(direction, clicks);
+        let password = &mut self.passwords[self.offset.start_for(0)];
+        *password = password.checked_add(1).ok_or(DialError::CounterOverflow)?;
+        Ok(())
+    }
+
+    fn passwords(&self) -> Result<Vec<u128>, DialError> {
+        Ok(self.passwords.clone())
+    }
+}
+
+/// The number of clicks that leave the dial pointing at 0, also known as method 0x434C49434B.
+pub struct PassedZero;
+
+impl PasswordMethod for PassedZero {
+    fn name(&self) -> &'static str {
+        "passed-zero"
+    }
+
+    fn count(&self, movement: &Movement) -> u128 {
+        movement.times_reached(0)
+    }
+
+    fn describe(&self, password: u128) -> String {
+        format!("The dial passed 0 {} times.", password)
+    }
+
+    fn passwords_by_start(&self, positions: u16) -> Option<Box<dyn PasswordsByStart>> {
+        Some(Box::new(PassedZeroByStart {
+            positions,
+            whole_turns: 0,
+            partial_turn_passes: vec![0; positions as usize + 1],
+            offset: Offset::new(positions),
+        }))
+    }
+}
+
+struct PassedZeroByStart {
+    positions: u16,
+    whole_turns: u128,
+    /// A difference array over starting positions, marking which starts reach 0 during a partial turn.
+    partial_turn_passes: Vec<i64>,
+    offset: Offset,
+}
+
+impl PasswordsByStart for PassedZeroByStart {
+    fn apply(&mut self, direction: Direction, clicks: u128) -> Result<(), DialError> {
+        let (p, positions) = (self.positions as u128, self.positions as usize);
+        self.whole_turns = self.whole_turns.checked_add(clicks / p).ok_or(DialError::CounterOverflow)?;
+
+        let partial_turn = clicks % p;
+        if partial_turn > 0 {
+            // The positions the dial can start this movement from and still reach 0 before it ends.
+            let first = match direction {
+                Direction::Left => 1,
+                Direction::Right => p - partial_turn,
+            };
+
+            let start = self.offset.start_for(first);
+            let end = start + partial_turn as usize;
+            self.partial_turn_passes[start] += 1;
+            if end <= positions {
+                self.partial_turn_passes[end] -= 1;
+            } else {
+                self.partial_turn_passes[positions] -= 1;
+                self.partial_turn_passes[0] += 1;
+                self.partial_turn_passes[end - positions] -= 1;
+            }
+        }
+
+        self.offset.apply(direction, clicks);
+        Ok(())
+    }
+
+    fn passwords(&self) -> Result<Vec<u128>, DialError> {
+        let mut passes = 0i64;
+        self.partial_turn_passes[..self.positions as usize].iter()
+            .map(|difference| {
+                passes += difference;
+                self.whole_turns.checked_add(passes as u128).ok_or(DialError::CounterOverflow)
+            })
+            .collect()
+    }
+}
+
+pub const METHOD_NAMES: [&str; 2] = ["stopped-at-zero", "passed-zero"];
+
+pub fn method_by_name(name: &str) -> Option<Rc<dyn PasswordMethod>> {
+    match name {
+        "stopped-at-zero" => Some(Rc::new(StoppedAtZero)),
+        "passed-zero" | "0x434C49434B" => Some(Rc::new(PassedZero)),
+        _ => None,
+    }
+}
+
+/// Every starting position from which the movements so far produce `target`.
+pub fn starting_positions(passwords: &dyn PasswordsByStart, target: u128) -> Result<Vec<u16>, DialError> {
+    Ok(passwords.passwords()?.into_iter()
+        .enumerate()
+        .filter(|(_, password)| *password == target)
+        .map(|(start, _)| start as u16)
+        .collect())
+}
+
+/// A password method along with the password it has counted so far.
+#[derive(Clone)]
+pub struct Password {
+    pub method: Rc<dyn PasswordMethod>,
+    pub count: u128,
+}
+
+impl Password {
+    pub fn new(method: Rc<dyn PasswordMethod>) -> Self {
+        Self { method, count: 0 }
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Password")
+            .field("method", &self.method.name())
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dial, Rotation};
+
+    #[test]
+    fn movements_can_be_followed() {
+        let movement = Movement::new(100, 50, Direction::Left, 68);
+        assert_eq!(movement.to, 82);
+
+        let movement = Movement::new(100, 52, Direction::Right, 48);
+        assert_eq!(movement.to, 0);
+
+        let movement = Movement::new(100, 0, Direction::Left, 5);
+        assert_eq!(movement.to, 95);
+    }
+
+    #[test]
+    fn any_position_can_be_counted() {
+        let movement = Movement::new(100, 50, Direction::Right, 250);
+        assert_eq!(movement.times_reached(0), 3);
+        assert_eq!(movement.times_reached(50), 2);
+        assert_eq!(movement.times_reached(99), 3);
+        assert_eq!(movement.times_reached(1), 2);
+
+        let movement = Movement::new(100, 50, Direction::Left, 250);
+        assert_eq!(movement.times_reached(0), 3);
+        assert_eq!(movement.times_reached(49), 3);
+        assert_eq!(movement.times_reached(50), 2);
+        assert_eq!(movement.times_reached(1), 3);
+
+        let movement = Movement::new(100, 0, Direction::Left, 0);
+        assert_eq!(movement.times_reached(0), 0);
+    }
+
+    #[test]
+    fn password_methods_can_be_found_by_name() {
+        for name in METHOD_NAMES {
+            assert!(method_by_name(name).is_some_and(|m| m.name() == name));
+        }
+
+        assert!(method_by_name("0x434C49434B").is_some_and(|m| m.name() == "passed-zero"));
+        assert!(method_by_name("clockwise").is_none());
+    }
+
+    fn passwords_by_start(method: &dyn PasswordMethod, positions: u16, movements: &[(Direction, u128)]) -> Box<dyn PasswordsByStart> {
+        let mut passwords = method.passwords_by_start(positions).unwrap();
+        for &(direction, clicks) in movements {
+            if passwords.apply(direction, clicks).is_err() {
+                break;
+            }
+        }
+        passwords
+    }
+
+    fn simulate_every_start(positions: u16, rotations: &[Rotation<u16>]) -> Vec<(u128, u128)> {
+        (0..positions)
+            .map(|start| {
+                let mut dial = Dial::with_positions(positions, start).ok().unwrap();
+                for rotation in rotations {
+                    dial = dial.rotate(*rotation).unwrap();
+                }
+                (dial.password("stopped-at-zero").unwrap(), dial.password("passed-zero").unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn passwords_by_start_match_simulating_every_start() {
+        let rotations = [
+            Rotation::Left(68u16),
+            Rotation::Left(30),
+            Rotation::Right(48),
+            Rotation::Left(5),
+            Rotation::Right(60),
+            Rotation::Left(55),
+            Rotation::Left(1),
+            Rotation::Left(99),
+            Rotation::Right(14),
+            Rotation::Left(82),
+            Rotation::Right(0),
+            Rotation::Left(1000),
+            Rotation::Right(359),
+        ];
+        let movements: Vec<(Direction, u128)> = rotations.iter().map(Rotation::direction_and_clicks).collect();
+
+        for positions in [1, 7, 60, 100, 360] {
+            let expected = simulate_every_start(positions, &rotations);
+            let stopped = passwords_by_start(&StoppedAtZero, positions, &movements).passwords().unwrap();
+            let passed = passwords_by_start(&PassedZero, positions, &movements).passwords().unwrap();
+
+            for start in 0..positions as usize {
+                assert_eq!((stopped[start], passed[start]), expected[start], "{} positions starting at {}", positions, start);
+            }
+        }
+    }
+
+    #[test]
+    fn starting_positions_can_be_solved() {
+        let rotations = [Rotation::Left(68u16), Rotation::Left(30), Rotation::Right(48), Rotation::Left(5), Rotation::Right(60)];
+        let movements: Vec<(Direction, u128)> = rotations.iter().map(Rotation::direction_and_clicks).collect();
+
+        let starts = starting_positions(passwords_by_start(&StoppedAtZero, 100, &movements).as_ref(), 1).unwrap();
+        assert!(starts.contains(&50));
+        assert!(starts.iter().all(|&start| simulate_every_start(100, &rotations)[start as usize].0 == 1));
+
+        let starts = starting_positions(passwords_by_start(&PassedZero, 100, &movements).as_ref(), 1000).unwrap();
+        assert!(starts.is_empty());
+    }
+
+    #[test]
+    fn passwords_too_large_to_count_are_errors() {
+        let mut passwords = PassedZero.passwords_by_start(1).unwrap();
+        assert_eq!(passwords.apply(Direction::Right, u128::MAX), Ok(()));
+        assert_eq!(passwords.apply(Direction::Right, u128::MAX), Err(DialError::CounterOverflow));
+
+        // The whole turns only just fit, but starting at 1 reaches 0 twice more during the partial turns.
+        let movements = [(Direction::Right, u128::MAX - 1), (Direction::Right, u128::MAX - 1), (Direction::Right, 1), (Direction::Right, 1), (Direction::Right, 1)];
+        assert_eq!(passwords_by_start(&PassedZero, 2, &movements).passwords(), Err(DialError::CounterOverflow));
+        assert_eq!(starting_positions(passwords_by_start(&PassedZero, 2, &movements).as_ref(), 0), Err(DialError::CounterOverflow));
+        assert_eq!(passwords_by_start(&PassedZero, 2, &movements[..4]).passwords(), Ok(vec![u128::MAX, u128::MAX]));
+    }
+
+    #[test]
+    fn built_in_methods_count_zeros() {
+        let movement = Movement::new(100, 52, Direction::Right, 148);
+        assert_eq!(StoppedAtZero.count(&movement), 1);
+        assert_eq!(PassedZero.count(&movement), 2);
+
+        let movement = Movement::new(100, 0, Direction::Left, 5);
+        assert_eq!(StoppedAtZero.count(&movement), 0);
+        assert_eq!(PassedZero.count(&movement), 0);
+    }
+}
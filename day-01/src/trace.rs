@@ -0,0 +1,198 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::{Clicks, Dial, Rotation};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    Csv,
+    JsonLines,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(TraceFormat::Csv),
+            "jsonl" => Ok(TraceFormat::JsonLines),
+            _ => Err(format!("Unknown trace format {:?}, expected \"csv\" or \"jsonl\".", s)),
+        }
+    }
+}
+
+/// What happened to the dial during a single rotation, along with the running totals afterwards.
+#[derive(Debug, PartialEq)]
+pub struct TraceRecord {
+    pub index: usize,
+    pub dial: usize,
+    pub rotation: String,
+    pub from_position: u16,
+    pub to_position: u16,
+    pub passwords: Vec<TracedPassword>,
+}
+
+/// How much a password method counted during the rotation, and its total afterwards.
+#[derive(Debug, PartialEq)]
+pub struct TracedPassword {
+    pub counted: u128,
+    pub total: u128,
+}
+
+impl TraceRecord {
+    pub fn new<T: Clicks>(index: usize, dial: usize, rotation: &Rotation<T>, before: &Dial, after: &Dial) -> Self {
+        Self {
+            index,
+            dial,
+            rotation: rotation.to_string(),
+            from_position: before.position,
+            to_position: after.position,
+            passwords: before.passwords.iter().zip(&after.passwords)
+                .map(|(before, after)| TracedPassword { counted: after.count - before.count, total: after.count })
+                .collect(),
+        }
+    }
+}
+
+pub struct TraceWriter<W: Write> {
+    writer: W,
+    format: TraceFormat,
+    columns: Vec<String>,
+}
+
+impl<W: Write> TraceWriter<W> {
+    /// Writes records with a column for each of the password methods called `methods`, in the order the
+    /// dials count them, followed by a running total column for each.
+    pub fn new(mut writer: W, format: TraceFormat, methods: &[&str]) -> io::Result<Self> {
+        let names: Vec<String> = methods.iter().map(|name| name.replace('-', "_")).collect();
+        let columns: Vec<String> = ["index", "dial", "rotation", "from_position", "to_position"].into_iter()
+            .map(String::from)
+            .chain(names.iter().cloned())
+            .chain(names.iter().map(|name| format!("total_{}", name)))
+            .collect();
+
+        if format == TraceFormat::Csv {
+            writeln!(writer, "{}", columns.join(","))?;
+        }
+
+        Ok(Self { writer, format, columns })
+    }
+
+    pub fn write(&mut self, r: &TraceRecord) -> io::Result<()> {
+        let values: Vec<String> = [r.index.to_string(), r.dial.to_string(), r.rotation.clone(), r.from_position.to_string(), r.to_position.to_string()]
+            .into_iter()
+            .chain(r.passwords.iter().map(|password| password.counted.to_string()))
+            .chain(r.passwords.iter().map(|password| password.total.to_string()))
+            .collect();
+
+        match self.format {
+            TraceFormat::Csv => writeln!(self.writer, "{}", values.join(",")),
+            TraceFormat::JsonLines => writeln!(self.writer, "{}", JsonRecord(&self.columns, &values)),
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+struct JsonRecord<'a>(&'a [String], &'a [String]);
+
+impl fmt::Display for JsonRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Column names are made of lowercase letters and underscores and every value is a number apart from
+        // the rotation, which only ever contains a direction letter and digits, so no escaping is required.
+        write!(f, "{{")?;
+        for (i, (column, value)) in self.0.iter().zip(self.1).enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            match column.as_str() {
+                "rotation" => write!(f, "\"{}\":\"{}\"", column, value)?,
+                _ => write!(f, "\"{}\":{}", column, value)?,
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::password;
+
+    fn trace(format: TraceFormat, rotations: Vec<Rotation<u16>>) -> (String, Dial) {
+        let mut dial = Dial::new(50);
+        let methods: Vec<&str> = dial.passwords.iter().map(|password| password.method.name()).collect();
+        let mut writer = TraceWriter::new(Vec::new(), format, &methods).unwrap();
+        for (index, rotation) in rotations.into_iter().enumerate() {
+            let rotated = dial.clone().rotate(rotation).unwrap();
+            writer.write(&TraceRecord::new(index + 1, 1, &rotation, &dial, &rotated)).unwrap();
+            dial = rotated;
+        }
+
+        (String::from_utf8(writer.finish().unwrap()).unwrap(), dial)
+    }
+
+    #[test]
+    fn csv_trace_can_be_written() {
+        let (output, _) = trace(TraceFormat::Csv, vec![Rotation::Left(68), Rotation::Left(30), Rotation::Right(48)]);
+        assert_eq!(output, "\
+index,dial,rotation,from_position,to_position,stopped_at_zero,passed_zero,total_stopped_at_zero,total_passed_zero
+1,1,L68,50,82,0,1,0,1
+2,1,L30,82,52,0,0,0,1
+3,1,R48,52,0,1,1,1,2
+");
+    }
+
+    #[test]
+    fn json_lines_trace_can_be_written() {
+        let (output, _) = trace(TraceFormat::JsonLines, vec![Rotation::Right(48), Rotation::Left(5)]);
+        assert_eq!(output, "\
+{\"index\":1,\"dial\":1,\"rotation\":\"R48\",\"from_position\":50,\"to_position\":98,\"stopped_at_zero\":0,\"passed_zero\":0,\"total_stopped_at_zero\":0,\"total_passed_zero\":0}
+{\"index\":2,\"dial\":1,\"rotation\":\"L5\",\"from_position\":98,\"to_position\":93,\"stopped_at_zero\":0,\"passed_zero\":0,\"total_stopped_at_zero\":0,\"total_passed_zero\":0}
+");
+    }
+
+    #[test]
+    fn trace_reconciles_with_the_final_totals() {
+        let rotations = vec![
+            Rotation::Left(68),
+            Rotation::Left(30),
+            Rotation::Right(48),
+            Rotation::Left(5),
+            Rotation::Right(60),
+            Rotation::Left(55),
+            Rotation::Left(1),
+            Rotation::Left(99),
+            Rotation::Right(14),
+            Rotation::Left(82),
+        ];
+        let (output, dial) = trace(TraceFormat::Csv, rotations);
+
+        let records: Vec<Vec<&str>> = output.lines().skip(1).map(|l| l.split(',').collect()).collect();
+        let stopped_at_zero: u128 = records.iter().map(|r| r[5].parse::<u128>().unwrap()).sum();
+        let passed_zero: u128 = records.iter().map(|r| r[6].parse::<u128>().unwrap()).sum();
+
+        assert_eq!(records.len(), 10);
+        assert_eq!(Some(stopped_at_zero), dial.password("stopped-at-zero"));
+        assert_eq!(Some(passed_zero), dial.password("passed-zero"));
+        assert_eq!(records[9][8], "6");
+    }
+
+    #[test]
+    fn trace_has_columns_for_the_chosen_methods() {
+        let methods = vec![password::method_by_name("passed-zero").unwrap()];
+        let dial = Dial::new(50).with_password_methods(methods);
+        let rotated = dial.clone().rotate(Rotation::Left(250u16)).unwrap();
+
+        let mut writer = TraceWriter::new(Vec::new(), TraceFormat::Csv, &["passed-zero"]).unwrap();
+        writer.write(&TraceRecord::new(1, 1, &Rotation::Left(250u16), &dial, &rotated)).unwrap();
+        assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(), "\
+index,dial,rotation,from_position,to_position,passed_zero,total_passed_zero
+1,1,L250,50,0,3,3
+");
+    }
+}
@@ -0,0 +1,327 @@
+use std::iter::FusedIterator;
+use std::ops::{Add, RangeInclusive, Sub};
+
+use crate::policy::RepetitionPolicy;
+
+/// How many invalid product ids were found, and what they add up to.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InvalidIdTotal {
+    pub count: u64,
+    pub sum: u128,
+}
+
+impl Add for InvalidIdTotal {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self { count: self.count + other.count, sum: self.sum + other.sum }
+    }
+}
+
+impl Sub for InvalidIdTotal {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self { count: self.count - other.count, sum: self.sum - other.sum }
+    }
+}
+
+fn digits(n: u64, radix: u32) -> u32 {
+    n.checked_ilog(radix as u64).map_or(1, |d| d + 1)
+}
+
+/// The blocks of `unit` digits that, repeated to make an id `length` digits long in `radix`, give
+/// an id between `from` and `to` (inclusive). Every such id is the block multiplied by 1 followed by
+/// `unit - 1` zeros, repeated; for example `abab` is `ab * 101`, so the blocks that fit can be worked
+/// out directly. Returns that multiplier along with the first and last block, which may be empty.
+/// A u64 has at most 64 digits in any radix, so none of this can overflow a u128.
+fn blocks(length: u32, unit: u32, from: u64, to: u64, radix: u32) -> (u128, RangeInclusive<u128>) {
+    let radix = radix as u128;
+    let multiplier = (radix.pow(length) - 1) / (radix.pow(unit) - 1);
+    let from = (from as u128).max(radix.pow(length - 1));
+    let to = (to as u128).min(radix.pow(length) - 1);
+
+    let first_block = from.div_ceil(multiplier).max(radix.pow(unit - 1));
+    let last_block = (to / multiplier).min(radix.pow(unit) - 1);
+
+    (multiplier, first_block..=last_block)
+}
+
+/// The ids between `from` and `to` (inclusive) that are `length` digits long in `radix` and made of
+/// a block of `unit` digits repeated.
+fn periodic_total(length: u32, unit: u32, from: u64, to: u64, radix: u32) -> InvalidIdTotal {
+    let (multiplier, blocks) = blocks(length, unit, from, to, radix);
+    if blocks.is_empty() {
+        return InvalidIdTotal::default();
+    }
+
+    let (first_block, last_block) = blocks.into_inner();
+    let count = last_block - first_block + 1;
+    InvalidIdTotal {
+        count: count as u64,
+        sum: multiplier * (first_block + last_block) * count / 2,
+    }
+}
+
+/// Totals the invalid ids between `from` and `to` (inclusive), written in `radix`, without visiting
+/// every id in between.
+pub fn invalid_id_total(from: u64, to: u64, policy: RepetitionPolicy, radix: u32) -> InvalidIdTotal {
+    let mut total = InvalidIdTotal::default();
+    if from > to {
+        return total;
+    }
+
+    for length in digits(from, radix)..=digits(to, radix) {
+        // An id such as 111111 repeats blocks of 1, 2 and 3 digits, so each id is only counted
+        // against its shortest repeating block. Those are found by removing, from the ids built
+        // from each block length, the ids already found for block lengths that divide it.
+        let units: Vec<u32> = (1..length).filter(|unit| length % unit == 0).collect();
+        let mut shortest: Vec<InvalidIdTotal> = Vec::with_capacity(units.len());
+        for (i, &unit) in units.iter().enumerate() {
+            let mut exact = periodic_total(length, unit, from, to, radix);
+            for (j, &smaller) in units[..i].iter().enumerate() {
+                if unit % smaller == 0 {
+                    exact = exact - shortest[j];
+                }
+            }
+            shortest.push(exact);
+
+            if policy.matches(unit, length / unit) {
+                total = total + exact;
+            }
+        }
+    }
+
+    total
+}
+
+/// An invalid product id, along with the shortest sequence of digits that it repeats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidId {
+    pub id: u64,
+    /// The repeated sequence of digits, such as 824 for 824824824.
+    pub unit: u64,
+    pub unit_length: u32,
+    pub repetitions: u32,
+}
+
+impl InvalidId {
+    pub fn is_invalid_under(&self, policy: RepetitionPolicy) -> bool {
+        policy.matches(self.unit_length, self.repetitions)
+    }
+}
+
+/// Whether a block of `unit` digits in `radix` is not itself some shorter sequence repeated, which
+/// is the case when it is not a multiple of any of the multipliers for the shorter sequences.
+fn is_primitive(block: u128, unit: u32, radix: u32) -> bool {
+    let radix = radix as u128;
+    (1..unit)
+        .filter(|smaller| unit.is_multiple_of(*smaller))
+        .all(|smaller| !block.is_multiple_of((radix.pow(unit) - 1) / (radix.pow(smaller) - 1)))
+}
+
+/// The ids made by repeating each of the blocks of one length that fit in a range, in ascending order.
+struct Blocks {
+    unit: u32,
+    repetitions: u32,
+    multiplier: u128,
+    next_block: u128,
+    last_block: u128,
+}
+
+impl Blocks {
+    /// The next id, skipping blocks that are themselves repeated, since their ids are produced from
+    /// their shortest repeating sequence instead.
+    fn peek(&mut self, radix: u32) -> Option<u128> {
+        while self.next_block <= self.last_block && !is_primitive(self.next_block, self.unit, radix) {
+            self.next_block += 1;
+        }
+
+        (self.next_block <= self.last_block).then(|| self.next_block * self.multiplier)
+    }
+}
+
+/// Every id between two ids, written in some radix, that repeats some sequence of digits at least
+/// twice, in ascending order. The ids are worked out one at a time from the blocks that are repeated,
+/// so even very large ranges are never held in memory.
+pub struct InvalidIds {
+    from: u64,
+    to: u64,
+    radix: u32,
+    length: u32,
+    last_length: u32,
+    blocks: Vec<Blocks>,
+}
+
+impl Iterator for InvalidIds {
+    type Item = InvalidId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Each id has a single shortest repeating sequence, so the ids made from blocks of
+            // different lengths never coincide and only need to be merged into ascending order.
+            let radix = self.radix;
+            let next = self.blocks.iter_mut()
+                .enumerate()
+                .filter_map(|(i, blocks)| blocks.peek(radix).map(|id| (id, i)))
+                .min();
+
+            if let Some((id, i)) = next {
+                let blocks = &mut self.blocks[i];
+                let block = blocks.next_block;
+                blocks.next_block += 1;
+                return Some(InvalidId { id: id as u64, unit: block as u64, unit_length: blocks.unit, repetitions: blocks.repetitions });
+            }
+
+            if self.length >= self.last_length {
+                return None;
+            }
+
+            self.length += 1;
+            let length = self.length;
+            self.blocks = (1..length)
+                .filter(|unit| length.is_multiple_of(*unit))
+                .map(|unit| {
+                    let (multiplier, blocks) = blocks(length, unit, self.from, self.to, self.radix);
+                    let (next_block, last_block) = blocks.into_inner();
+                    Blocks { unit, repetitions: length / unit, multiplier, next_block, last_block }
+                })
+                .collect();
+        }
+    }
+}
+
+impl FusedIterator for InvalidIds {}
+
+/// Every id between `from` and `to` (inclusive), written in `radix`, that repeats some sequence of
+/// digits at least twice, in ascending order.
+pub fn invalid_ids(from: u64, to: u64, radix: u32) -> InvalidIds {
+    let (length, last_length) = match from <= to {
+        true => (digits(from, radix) - 1, digits(to, radix)),
+        false => (0, 0),
+    };
+
+    InvalidIds { from, to, radix, length, last_length, blocks: Vec::new() }
+}
+
+/// Every id up to and including `bound`, written in `radix`, that is invalid under `policy`, in
+/// ascending order. An id that can be read as several sequences repeated, such as `111111`, is only
+/// produced once.
+pub fn invalid_ids_up_to(bound: u64, policy: RepetitionPolicy, radix: u32) -> impl Iterator<Item = InvalidId> {
+    invalid_ids(0, bound, radix).filter(move |invalid_id| invalid_id.is_invalid_under(policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_built_from_a_block_can_be_totalled() {
+        assert_eq!(periodic_total(4, 2, 1000, 9999, 10), InvalidIdTotal { count: 90, sum: (10..100).sum::<u128>() * 101 });
+        assert_eq!(periodic_total(4, 2, 1011, 1313, 10), InvalidIdTotal { count: 3, sum: 1111 + 1212 + 1313 });
+        assert_eq!(periodic_total(6, 3, 222220, 222224, 10), InvalidIdTotal { count: 1, sum: 222222 });
+        assert_eq!(periodic_total(6, 3, 222223, 222224, 10), InvalidIdTotal::default());
+    }
+
+    #[test]
+    fn ids_are_counted_once_whatever_their_shortest_block() {
+        let total = invalid_id_total(111111, 111111, RepetitionPolicy::AT_LEAST_TWICE, 10);
+        assert_eq!(total, InvalidIdTotal { count: 1, sum: 111111 });
+
+        let total = invalid_id_total(100000, 999999, RepetitionPolicy::AT_LEAST_TWICE, 10);
+        assert_eq!(total.count, 9 + 90 + 900 - 9 - 9);
+    }
+
+    #[test]
+    fn wide_ranges_can_be_totalled() {
+        let total = invalid_id_total(0, u64::MAX, RepetitionPolicy::TWICE, 10);
+        assert_eq!(total.count, (1..=9).map(|half| 9 * 10u64.pow(half - 1)).sum::<u64>() + 844_674_408);
+
+        let total = invalid_id_total(10_000_000_000_000_000_000, u64::MAX, RepetitionPolicy::TWICE, 10);
+        assert_eq!(total.count, 844_674_408);
+        assert_eq!(total.sum, (1_000_000_000 + 1_844_674_407) * 844_674_408 / 2 * 10_000_000_001);
+    }
+
+    #[test]
+    fn invalid_ids_are_listed_with_their_shortest_unit() {
+        assert_eq!(invalid_ids(95, 115, 10).collect::<Vec<InvalidId>>(), vec![
+            InvalidId { id: 99, unit: 9, unit_length: 1, repetitions: 2 },
+            InvalidId { id: 111, unit: 1, unit_length: 1, repetitions: 3 },
+        ]);
+        assert_eq!(invalid_ids(111110, 121212, 10).collect::<Vec<InvalidId>>(), vec![
+            InvalidId { id: 111111, unit: 1, unit_length: 1, repetitions: 6 },
+            InvalidId { id: 112112, unit: 112, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 113113, unit: 113, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 114114, unit: 114, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 115115, unit: 115, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 116116, unit: 116, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 117117, unit: 117, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 118118, unit: 118, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 119119, unit: 119, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 120120, unit: 120, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 121121, unit: 121, unit_length: 3, repetitions: 2 },
+            InvalidId { id: 121212, unit: 12, unit_length: 2, repetitions: 3 },
+        ]);
+        assert_eq!(invalid_ids(824824821, 824824827, 10).collect::<Vec<InvalidId>>(), vec![InvalidId { id: 824824824, unit: 824, unit_length: 3, repetitions: 3 }]);
+        assert!(!InvalidId { id: 824824824, unit: 824, unit_length: 3, repetitions: 3 }.is_invalid_under(RepetitionPolicy::TWICE));
+        assert!(InvalidId { id: 1111, unit: 1, unit_length: 1, repetitions: 4 }.is_invalid_under(RepetitionPolicy::TWICE));
+    }
+
+    #[test]
+    fn invalid_ids_agree_with_the_totals() {
+        let policies = [
+            RepetitionPolicy::TWICE,
+            RepetitionPolicy::AT_LEAST_TWICE,
+            RepetitionPolicy::Exactly(3),
+            RepetitionPolicy::AtLeast(4),
+            RepetitionPolicy::UnitLength { min: 2, max: 3 },
+        ];
+
+        for radix in [2, 10, 16] {
+            let ids: Vec<InvalidId> = invalid_ids(0, 2_000_000, radix).collect();
+            for policy in policies {
+                let total = ids.iter().filter(|i| i.is_invalid_under(policy))
+                    .fold(InvalidIdTotal::default(), |total, i| total + InvalidIdTotal { count: 1, sum: i.id as u128 });
+                assert_eq!(total, invalid_id_total(0, 2_000_000, policy, radix), "{:?} {}", policy, radix);
+            }
+        }
+    }
+
+    #[test]
+    fn ids_are_totalled_under_any_policy() {
+        // 111111 is 1 six times, 11 three times and 111 twice, while 121212 is only 12 three times.
+        let total = invalid_id_total(111111, 121212, RepetitionPolicy::Exactly(3), 10);
+        assert_eq!(total, InvalidIdTotal { count: 2, sum: 111111 + 121212 });
+
+        let total = invalid_id_total(1, 99_999, RepetitionPolicy::AtLeast(5), 10);
+        assert_eq!(total, InvalidIdTotal { count: 9, sum: (1..=9).sum::<u128>() * 11111 });
+
+        let total = invalid_id_total(1, 9999, RepetitionPolicy::UnitLength { min: 2, max: 2 }, 10);
+        assert_eq!(total.count, 90);
+    }
+
+    #[test]
+    fn invalid_ids_are_generated_in_order_without_duplicates() {
+        for radix in [2, 10, 36] {
+            let ids: Vec<u64> = invalid_ids(0, 3_000_000, radix).map(|invalid_id| invalid_id.id).collect();
+            assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        let ids: Vec<u64> = invalid_ids_up_to(1_000_000, RepetitionPolicy::TWICE, 10).map(|invalid_id| invalid_id.id).collect();
+        assert_eq!(ids.len(), 9 + 90 + 900);
+        assert_eq!(ids[..3], [11, 22, 33]);
+        assert_eq!(ids.iter().filter(|&&id| id == 111111).count(), 1);
+        assert_eq!(ids.last(), Some(&999999));
+
+        let ids: Vec<u64> = invalid_ids_up_to(1111, RepetitionPolicy::AT_LEAST_TWICE, 10).skip(16).map(|invalid_id| invalid_id.id).collect();
+        assert_eq!(ids, vec![888, 999, 1010, 1111]);
+    }
+
+    #[test]
+    fn invalid_ids_can_be_generated_up_to_the_largest_id() {
+        let mut ids = invalid_ids(u64::MAX - 10_000_000_000, u64::MAX, 10);
+        assert_eq!(ids.next().map(|invalid_id| invalid_id.id), Some(18446744071844674407));
+        assert_eq!(ids.next(), None);
+        assert_eq!(invalid_ids(5, 4, 10).next(), None);
+    }
+}
@@ -1,34 +1,222 @@
+mod invalid_ids;
+mod parallel;
+mod policy;
+#[cfg(test)]
+mod properties;
+mod report;
+
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::fmt;
+use std::io::{self, BufWriter, Read, Write};
+use std::iter::FusedIterator;
 use std::str::FromStr;
+use std::thread;
+
+use invalid_ids::InvalidIdTotal;
+use policy::RepetitionPolicy;
+use report::{RangeReport, ReportFormat};
+
+/// More threads than this would spend longer starting up than checking ids.
+const MAX_THREADS: usize = 1024;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
 
-    let file_path = &args[1];
-    let path = Path::new(file_path);
-    let product_id_ranges = ProductIdRange::from_file(path.to_str().unwrap());
+    if let Some(bound) = options.generate_bound {
+        let policy = options.policy.unwrap_or(RepetitionPolicy::AT_LEAST_TWICE);
+        let written = match &options.output_path {
+            Some(path) => File::create(path).and_then(| file | generate(BufWriter::new(file), bound, policy, options.radix)),
+            None => generate(BufWriter::new(io::stdout().lock()), bound, policy, options.radix),
+        };
 
-    if product_id_ranges.is_err() {
-        println!("Unable to read the file {:?}", file_path);
+        if let Err(e) = written {
+            println!("Unable to write the invalid product ids: {}", e);
+        }
+        return;
+    }
+
+    let file_path = options.file_path.unwrap_or_default();
+    let product_id_ranges = match ProductIdRange::from_file(&file_path, options.radix) {
+        Ok(product_id_ranges) => ProductIdRange::merge_overlapping(product_id_ranges),
+        Err(RangeFileError::InvalidRanges(invalid_ranges)) => {
+            for invalid_range in invalid_ranges {
+                println!("{}", invalid_range);
+            }
+            return;
+        },
+        Err(_) => {
+            println!("Unable to read the file {:?}", file_path);
+            return;
+        },
+    };
+
+    if let Some(format) = options.report_format {
+        let policy = options.policy.unwrap_or(RepetitionPolicy::AT_LEAST_TWICE);
+        let reports: Vec<RangeReport> = product_id_ranges.iter().map(| &r | RangeReport::new(r, policy, options.radix)).collect();
+        if let Err(e) = report::write_report(io::stdout().lock(), &reports, options.radix, format) {
+            println!("Unable to write the report: {}", e);
+        }
         return;
     }
 
-    let invalid_product_id_sum = product_id_ranges.unwrap().into_iter()
-        .flat_map(| r | r.into_iter())
-        .filter(| p | p.repeats_sequence_of_digits())
-        .sum::<u64>();
+    let policies = match options.policy {
+        Some(policy) => vec![policy],
+        None => vec![RepetitionPolicy::TWICE, RepetitionPolicy::AT_LEAST_TWICE],
+    };
+
+    let threads = options.threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, | threads | threads.get()));
+
+    for policy in policies {
+        let total = parallel::invalid_id_total(&product_id_ranges, threads, | r | match options.brute_force {
+            true => r.brute_force_invalid_id_total(policy, options.radix),
+            false => r.invalid_id_total(policy, options.radix),
+        });
+
+        println!("The file has {} invalid product ids repeating {}, with a sum of {}.", total.count, policy, total.sum);
+    }
+}
+
+/// Writes out every id up to and including `bound` that is invalid under `policy`, one per line in
+/// `radix`, in ascending order.
+fn generate<W: Write>(mut writer: W, bound: u64, policy: RepetitionPolicy, radix: u32) -> io::Result<()> {
+    for invalid_id in invalid_ids::invalid_ids_up_to(bound, policy, radix) {
+        writeln!(writer, "{}", format_in_radix(invalid_id.id, radix))?;
+    }
+
+    writer.flush()
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    /// Where to read product id ranges from, which is only `None` when generating invalid ids.
+    file_path: Option<String>,
+    /// Check every id in every range instead of working the invalid ids out directly. This is much
+    /// slower, but is kept as a reference to check the arithmetic against.
+    brute_force: bool,
+    /// The radix that product ids are written in, from 2 to 36.
+    radix: u32,
+    /// List every invalid id in this format instead of only giving the totals.
+    report_format: Option<ReportFormat>,
+    /// Only look for ids that are invalid under this policy, instead of both of the puzzle's rules.
+    policy: Option<RepetitionPolicy>,
+    /// How many threads to split the ranges between, or `None` for as many as the machine can run at once.
+    threads: Option<usize>,
+    /// Generate every invalid id up to and including this bound instead of checking a file of ranges.
+    generate_bound: Option<u64>,
+    /// Where to write generated ids to, or `None` for standard output.
+    output_path: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut file_path = None;
+        let mut brute_force = false;
+        let mut radix = 10;
+        let mut report_format = None;
+        let mut policy = None;
+        let mut threads = None;
+        let mut generate_bound = None;
+        let mut output_path = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--brute-force" => brute_force = true,
+                "--radix" => {
+                    let value = Self::required_value(arg, args.next())?;
+                    radix = value.parse::<u32>().ok()
+                        .filter(| radix | (2..=36).contains(radix))
+                        .ok_or_else(|| format!("The option {} expects a whole number between 2 and 36, not {:?}.", arg, value))?;
+                },
+                "--report" => report_format = Some(ReportFormat::from_str(Self::required_value(arg, args.next())?)?),
+                "--policy" => policy = Some(RepetitionPolicy::from_str(Self::required_value(arg, args.next())?)?),
+                "--threads" => {
+                    let value = Self::required_value(arg, args.next())?;
+                    threads = Some(value.parse::<usize>().ok()
+                        .filter(| threads | (1..=MAX_THREADS).contains(threads))
+                        .ok_or_else(|| format!("The option {} expects a whole number from 1 to {}, not {:?}.", arg, MAX_THREADS, value))?);
+                },
+                "--generate" => generate_bound = Some(Self::required_value(arg, args.next())?),
+                "--output" => output_path = Some(Self::required_value(arg, args.next())?.clone()),
+                flag if flag.starts_with("--") => return Err(format!("Unknown option {:?}.", flag)),
+                path if file_path.is_none() => file_path = Some(path.to_string()),
+                extra => return Err(format!("Unexpected argument {:?}.", extra)),
+            }
+        }
+
+        // The bound is written in the radix, which may be given after it.
+        let generate_bound = generate_bound
+            .map(| bound | u64::from_str_radix(bound, radix)
+                .map_err(|_| format!("The option --generate expects a product id in radix {}, not {:?}.", radix, bound)))
+            .transpose()?;
+
+        match (generate_bound, &file_path, &output_path) {
+            (Some(_), Some(path), _) => return Err(format!("Unexpected argument {:?}, as --generate does not read a file.", path)),
+            (None, None, _) => return Err("Expected the path of a file of product id ranges.".to_string()),
+            (None, _, Some(_)) => return Err("The option --output can only be used with --generate.".to_string()),
+            _ => {},
+        }
+
+        Ok(Self { file_path, brute_force, radix, report_format, policy, threads, generate_bound, output_path })
+    }
+
+    fn required_value<'a>(flag: &str, value: Option<&'a String>) -> Result<&'a String, String> {
+        value.ok_or_else(|| format!("The option {} requires a value.", flag))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RangeParsingError {
+    /// The range is not two ids separated by a `-`.
+    MissingSeparator,
+    InvalidId(String),
+    /// The first id is greater than the last.
+    Reversed,
+}
 
-    println!("The file has and invalid product id sum of {}.", invalid_product_id_sum);
+impl fmt::Display for RangeParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeParsingError::MissingSeparator => write!(f, "expected two ids separated by '-'"),
+            RangeParsingError::InvalidId(id) => write!(f, "{:?} is not a valid id", id),
+            RangeParsingError::Reversed => write!(f, "the first id is greater than the last"),
+        }
+    }
 }
 
-#[derive(Debug)]
-struct FileNotFoundError;
-struct RangeParsingError;
+/// A range in a file that could not be parsed, along with where it starts.
+#[derive(Debug, PartialEq)]
+struct InvalidRange {
+    line: usize,
+    column: usize,
+    text: String,
+    reason: RangeParsingError,
+}
+
+impl fmt::Display for InvalidRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {:?} is not a valid range ({})", self.line, self.column, self.text, self.reason)
+    }
+}
 
 #[derive(Debug, PartialEq)]
+enum RangeFileError {
+    FileNotFound,
+    Unreadable,
+    /// Every range in the file that could not be parsed.
+    InvalidRanges(Vec<InvalidRange>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct ProductIdRange {
     from: u64,
     to: u64
@@ -41,112 +229,243 @@ impl ProductIdRange {
         Self { from, to }
     }
 
-    fn from_file(file_path: &str) -> Result<Vec<ProductIdRange>, FileNotFoundError> {
-        let file = File::open(file_path);
-        if file.is_err() {
-            return Err(FileNotFoundError);
-        }
+    fn from_file(file_path: &str, radix: u32) -> Result<Vec<ProductIdRange>, RangeFileError> {
+        let mut file = File::open(file_path).map_err(|_| RangeFileError::FileNotFound)?;
+        let mut text = String::new();
+        file.read_to_string(&mut text).map_err(|_| RangeFileError::Unreadable)?;
 
-        let mut reader = BufReader::new(file.unwrap());
-        let mut line = String::new();
-        let read = reader.read_line(&mut line);
-
-        if read.is_ok() {
-            let mut product_id_ranges = Vec::new();
-
-            // Strip BOM if present
-            let clean = line.trim_start_matches('\u{feff}');
-            for raw_product_id_range in clean.split(',') {
-                match ProductIdRange::from_str(raw_product_id_range) {
-                    Ok(product_id_range) => {
-                        product_id_ranges.push(product_id_range);
-                    },
-                    Err(_) => continue
+        Self::parse_all(&text, radix).map_err(RangeFileError::InvalidRanges)
+    }
+
+    /// Parses comma separated ranges, which may be spread over several lines with any whitespace
+    /// around them. Every range that cannot be parsed is returned, with the line and column it starts at.
+    fn parse_all(text: &str, radix: u32) -> Result<Vec<ProductIdRange>, Vec<InvalidRange>> {
+        let mut product_id_ranges = Vec::new();
+        let mut invalid_ranges = Vec::new();
+
+        // Strip BOM if present
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+        let (mut line, mut column) = (1, 1);
+        let mut start = None;
+        for (offset, c) in text.char_indices().chain(std::iter::once((text.len(), ','))) {
+            if c == ',' {
+                // Empty entries, such as after a trailing comma, are ignored.
+                if let Some((start_offset, start_line, start_column)) = start.take() {
+                    let raw_product_id_range = text[start_offset..offset].trim_end();
+                    match ProductIdRange::from_str_radix(raw_product_id_range, radix) {
+                        Ok(product_id_range) => product_id_ranges.push(product_id_range),
+                        Err(reason) => invalid_ranges.push(InvalidRange {
+                            line: start_line,
+                            column: start_column,
+                            text: raw_product_id_range.to_string(),
+                            reason,
+                        }),
+                    }
                 }
+            } else if start.is_none() && !c.is_whitespace() {
+                start = Some((offset, line, column));
             }
 
-            return Ok(product_id_ranges);
+            match c {
+                '\n' => (line, column) = (line + 1, 1),
+                _ => column += 1,
+            }
         }
 
-        Err(FileNotFoundError)
+        match invalid_ranges.is_empty() {
+            true => Ok(product_id_ranges),
+            false => Err(invalid_ranges),
+        }
     }
-}
 
-impl FromStr for ProductIdRange {
-    type Err = RangeParsingError;
+    /// Sorts the ranges and combines any that overlap, so that no id is counted twice.
+    fn merge_overlapping(mut product_id_ranges: Vec<ProductIdRange>) -> Vec<ProductIdRange> {
+        product_id_ranges.sort_by_key(| r | (r.from, r.to));
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut merged: Vec<ProductIdRange> = Vec::with_capacity(product_id_ranges.len());
+        for r in product_id_ranges {
+            match merged.last_mut() {
+                Some(last) if r.from <= last.to => last.to = last.to.max(r.to),
+                _ => merged.push(r),
+            }
+        }
+
+        merged
+    }
+
+    /// The ids in the range, without consuming it.
+    fn iter(&self) -> ProductIds {
+        ProductIds { next: self.from, last: self.to, finished: false }
+    }
+
+    /// Parses a range such as `ff-1a0`, with both ids written in the given radix.
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, RangeParsingError> {
         let parts: Vec<&str> = s.split('-').collect();
 
         if parts.len() != 2 {
-            return Err(RangeParsingError);
+            return Err(RangeParsingError::MissingSeparator);
         }
 
-        let from = parts[0].parse::<u64>().map_err(|_| RangeParsingError)?;
-        let to = parts[1].parse::<u64>().map_err(|_| RangeParsingError)?;
+        let parse = | id: &str | u64::from_str_radix(id, radix).map_err(|_| RangeParsingError::InvalidId(id.to_string()));
+        let from = parse(parts[0])?;
+        let to = parse(parts[1])?;
+
+        if from > to {
+            return Err(RangeParsingError::Reversed);
+        }
 
         Ok(Self::new(from, to))
     }
+
+    fn invalid_id_total(&self, policy: RepetitionPolicy, radix: u32) -> InvalidIdTotal {
+        invalid_ids::invalid_id_total(self.from, self.to, policy, radix)
+    }
+
+    fn brute_force_invalid_id_total(&self, policy: RepetitionPolicy, radix: u32) -> InvalidIdTotal {
+        self.iter()
+            .filter(| p | p.repeats_sequence_of_digits_under(policy, radix))
+            .fold(InvalidIdTotal::default(), | total, p | total + InvalidIdTotal { count: 1, sum: p as u128 })
+    }
+}
+
+impl FromStr for ProductIdRange {
+    type Err = RangeParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_radix(s, 10)
+    }
 }
 
 impl IntoIterator for ProductIdRange {
     type Item = u64;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = ProductIds;
 
     fn into_iter(self) -> Self::IntoIter {
-        (self.from..=self.to).collect::<Vec<u64>>().into_iter()
+        self.iter()
     }
 }
 
-trait RepeatedDigitSequenceDetector {
-    fn repeats_sequence_of_digits_twice(self) -> bool;
+impl IntoIterator for &ProductIdRange {
+    type Item = u64;
+    type IntoIter = ProductIds;
 
-    fn repeats_sequence_of_digits(self) -> bool;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The ids in a `ProductIdRange`, produced one at a time as they are needed.
+#[derive(Debug, Clone)]
+struct ProductIds {
+    next: u64,
+    last: u64,
+    finished: bool,
 }
 
-impl RepeatedDigitSequenceDetector for u64 {
-    fn repeats_sequence_of_digits_twice(self) -> bool {
-        let s = self.to_string();
-        if s.len() % 2 == 1 {
-            // A string must have an even length to contain a repeated digit sequence.
-            return false;
+impl Iterator for ProductIds {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.take_range(1).map(| r | r.from)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining()) {
+            Ok(remaining) => (remaining, Some(remaining)),
+            Err(_) => (usize::MAX, None),
         }
+    }
+}
 
-        let (first, last) = s.split_at(s.len() / 2);
-        first.parse::<u64>().unwrap() == last.parse::<u64>().unwrap()
-    }
-
-    fn repeats_sequence_of_digits(self) -> bool {
-        let s = self.to_string();
-        let clean_divisors = match s.len() {
-            0 | 1 => vec![1],
-            2 => vec![1, 2],
-            3 => vec![1, 3],
-            4 => vec![1, 2, 4],
-            5 => vec![1, 5],
-            6 => vec![1, 2, 3, 6],
-            7 => vec![1, 7],
-            8 => vec![1, 2, 4, 8],
-            9 => vec![1, 3, 9],
-            10 => vec![1, 2, 5, 10],
-            _ => vec![],
-        };
-        
-        for divisor in clean_divisors {
-            let segments: Vec<&str> = s.as_bytes()
-                .chunks(divisor)
-                .map(| chunk | std::str::from_utf8(chunk).unwrap())
-                .collect();
-
-            if segments.windows(2).all(| w | w[0] == w[1]) && segments.len() > 1 {
-                return true;
-            }
+impl ProductIds {
+    /// How many ids are left, for showing progress. This takes the place of `ExactSizeIterator`,
+    /// whose `len()` is a `usize`: the range `0..=u64::MAX` has one more id than a 64-bit `usize` can
+    /// count, so `len()` could only be wrong or panic there, while a `u128` always holds the count.
+    pub fn remaining(&self) -> u128 {
+        match self.finished {
+            true => 0,
+            false => (self.last - self.next) as u128 + 1,
+        }
+    }
+
+    /// Takes up to `count` of the ids left, as a range of their own, or `None` if there are none.
+    pub fn take_range(&mut self, count: u128) -> Option<ProductIdRange> {
+        if count == 0 || self.finished {
+            return None;
+        }
+
+        let from = self.next;
+        let to = from + (count.min(self.remaining()) - 1) as u64;
+        // The range may end at u64::MAX, so stop on the last id rather than stepping past it.
+        match to == self.last {
+            true => self.finished = true,
+            false => self.next = to + 1,
         }
 
-        false
+        Some(ProductIdRange::new(from, to))
+    }
+}
+
+impl FusedIterator for ProductIds {}
+
+trait RepeatedDigitSequenceDetector: Sized {
+    /// Whether the number, written in `radix` from 2 to 36, is invalid under `policy`.
+    fn repeats_sequence_of_digits_under(self, policy: RepetitionPolicy, radix: u32) -> bool;
+}
+
+/// The digits of `n` written in `radix`, most significant first.
+fn digits_in_radix(mut n: u128, radix: u32) -> Vec<u8> {
+    assert!((2..=36).contains(&radix), "radix must be between 2 and 36, not {}", radix);
+
+    let mut digits = Vec::new();
+    loop {
+        digits.push((n % radix as u128) as u8);
+        n /= radix as u128;
+        if n == 0 {
+            break;
+        }
     }
+
+    digits.reverse();
+    digits
+}
+
+/// Writes `n` out in `radix`, using lowercase letters for the digits above 9.
+fn format_in_radix(n: u64, radix: u32) -> String {
+    digits_in_radix(n as u128, radix).into_iter()
+        .map(| digit | char::from_digit(digit as u32, radix).unwrap())
+        .collect()
 }
 
+/// The length of the shortest sequence of digits that, repeated, makes up the whole of `digits`.
+/// Only sequences whose length divides the number of digits can be repeated to make up the whole string.
+fn shortest_repeating_length(digits: &[u8]) -> usize {
+    (1..digits.len())
+        .filter(| length | digits.len().is_multiple_of(*length))
+        .find(| &length | {
+            let segments: Vec<&[u8]> = digits.chunks(length).collect();
+            segments.windows(2).all(| w | w[0] == w[1])
+        })
+        .unwrap_or(digits.len())
+}
+
+macro_rules! impl_repeated_digit_sequence_detector {
+    ($($t:ty),*) => {
+        $(
+            impl RepeatedDigitSequenceDetector for $t {
+                fn repeats_sequence_of_digits_under(self, policy: RepetitionPolicy, radix: u32) -> bool {
+                    let digits = digits_in_radix(self as u128, radix);
+                    let unit_length = shortest_repeating_length(&digits);
+                    policy.matches(unit_length as u32, (digits.len() / unit_length) as u32)
+                }
+            }
+        )*
+    };
+}
+
+impl_repeated_digit_sequence_detector!(u8, u16, u32, u64, u128, usize);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,6 +487,58 @@ mod tests {
         assert!(product_id_range.is_err());
     }
 
+    #[test]
+    fn product_id_ranges_can_be_spread_over_several_lines() {
+        let text = "\u{feff}11-22,95-115,\n  998-1012 ,\r\n\t1188511880-1188511890,\n\n222220-222224,\n";
+        assert_eq!(ProductIdRange::parse_all(text, 10), Ok(vec![
+            ProductIdRange::new(11, 22),
+            ProductIdRange::new(95, 115),
+            ProductIdRange::new(998, 1012),
+            ProductIdRange::new(1188511880, 1188511890),
+            ProductIdRange::new(222220, 222224),
+        ]));
+    }
+
+    #[test]
+    fn invalid_product_id_ranges_are_reported_with_their_positions() {
+        let text = "11-22,22-11,\n  95-115, 1x-3\n,998 1012,-";
+        assert_eq!(ProductIdRange::parse_all(text, 10), Err(vec![
+            InvalidRange { line: 1, column: 7, text: "22-11".to_string(), reason: RangeParsingError::Reversed },
+            InvalidRange { line: 2, column: 11, text: "1x-3".to_string(), reason: RangeParsingError::InvalidId("1x".to_string()) },
+            InvalidRange { line: 3, column: 2, text: "998 1012".to_string(), reason: RangeParsingError::MissingSeparator },
+            InvalidRange { line: 3, column: 11, text: "-".to_string(), reason: RangeParsingError::InvalidId("".to_string()) },
+        ]));
+
+        let invalid_range = InvalidRange { line: 1, column: 7, text: "22-11".to_string(), reason: RangeParsingError::Reversed };
+        assert_eq!(invalid_range.to_string(), "line 1, column 7: \"22-11\" is not a valid range (the first id is greater than the last)");
+    }
+
+    #[test]
+    fn product_id_range_files_can_be_read() {
+        let product_id_ranges = ProductIdRange::from_file("data/example.txt", 10);
+        assert!(product_id_ranges.is_ok_and(| r | r.len() == 11));
+
+        let product_id_ranges = ProductIdRange::from_file("data/missing.txt", 10);
+        assert_eq!(product_id_ranges, Err(RangeFileError::FileNotFound));
+    }
+
+    #[test]
+    fn overlapping_product_id_ranges_are_merged() {
+        let merged = ProductIdRange::merge_overlapping(vec![
+            ProductIdRange::new(95, 115),
+            ProductIdRange::new(11, 22),
+            ProductIdRange::new(100, 120),
+            ProductIdRange::new(15, 20),
+            ProductIdRange::new(121, 130),
+            ProductIdRange::new(120, 120),
+        ]);
+        assert_eq!(merged, vec![ProductIdRange::new(11, 22), ProductIdRange::new(95, 120), ProductIdRange::new(121, 130)]);
+
+        let total = merged.iter()
+            .fold(InvalidIdTotal::default(), | total, r | total + r.invalid_id_total(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert_eq!(total, InvalidIdTotal { count: 4, sum: 11 + 22 + 99 + 111 });
+    }
+
     #[test]
     fn product_id_ranges_can_be_enumerated() {
         let r = ProductIdRange::new(1, 1);
@@ -179,37 +550,156 @@ mod tests {
         assert_eq!(product_ids, vec![11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22]);
     }
 
+    #[test]
+    fn product_id_ranges_can_be_enumerated_without_being_consumed() {
+        let r = ProductIdRange::new(11, 13);
+        assert_eq!(r.iter().collect::<Vec<u64>>(), vec![11, 12, 13]);
+        assert_eq!((&r).into_iter().sum::<u64>(), 36);
+        assert_eq!(r, ProductIdRange::new(11, 13));
+    }
+
+    #[test]
+    fn product_id_ranges_are_enumerated_lazily() {
+        let r = ProductIdRange::new(u64::MAX - 1_000_000_000_000, u64::MAX);
+        let mut ids = r.iter();
+        assert_eq!(ids.remaining(), 1_000_000_000_001);
+        assert_eq!(ids.next(), Some(u64::MAX - 1_000_000_000_000));
+        assert_eq!(ids.remaining(), 1_000_000_000_000);
+
+        let mut ids = ProductIdRange::new(0, u64::MAX).iter();
+        assert_eq!(ids.remaining(), u64::MAX as u128 + 1);
+        assert_eq!(ids.size_hint(), (usize::MAX, None));
+        assert_eq!(ids.next(), Some(0));
+        assert_eq!(ids.remaining(), u64::MAX as u128);
+
+        let mut ids = ProductIdRange::new(u64::MAX - 1, u64::MAX).iter();
+        assert_eq!(ids.size_hint(), (2, Some(2)));
+        assert_eq!(ids.next(), Some(u64::MAX - 1));
+        assert_eq!(ids.next(), Some(u64::MAX));
+        assert_eq!(ids.size_hint(), (0, Some(0)));
+        assert_eq!(ids.remaining(), 0);
+        assert_eq!(ids.next(), None);
+        assert_eq!(ids.next(), None);
+    }
+
+    #[test]
+    fn product_ids_can_be_taken_as_ranges() {
+        let mut ids = ProductIdRange::new(u64::MAX - 4, u64::MAX).iter();
+        assert_eq!(ids.take_range(0), None);
+        assert_eq!(ids.take_range(2), Some(ProductIdRange::new(u64::MAX - 4, u64::MAX - 3)));
+        assert_eq!(ids.next(), Some(u64::MAX - 2));
+        assert_eq!(ids.take_range(u128::MAX), Some(ProductIdRange::new(u64::MAX - 1, u64::MAX)));
+        assert_eq!(ids.take_range(1), None);
+        assert_eq!(ids.next(), None);
+    }
+
+    #[test]
+    fn remaining_ids_can_be_counted_up_to_the_largest_id() {
+        assert_eq!(ProductIdRange::new(0, u64::MAX).iter().remaining(), 1 << 64);
+        assert_eq!(ProductIdRange::new(1, u64::MAX).iter().remaining(), u64::MAX as u128);
+        assert_eq!(ProductIdRange::new(0, u64::MAX - 1).iter().remaining(), u64::MAX as u128);
+        assert_eq!(ProductIdRange::new(u64::MAX, u64::MAX).iter().remaining(), 1);
+
+        // Once the largest id is reached, nothing is left rather than the count wrapping around.
+        let mut ids = ProductIdRange::new(u64::MAX, u64::MAX).iter();
+        assert_eq!(ids.next(), Some(u64::MAX));
+        assert_eq!(ids.remaining(), 0);
+        assert_eq!(ids.size_hint(), (0, Some(0)));
+    }
+
     #[test]
     fn u64_can_detect_repeated_digit_sequences() {
-        let n = 11;
-        assert!(n.repeats_sequence_of_digits_twice());
+        let n: u64 = 11;
+        assert!(n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
 
-        let n = 1010;
-        assert!(n.repeats_sequence_of_digits_twice());
+        let n: u64 = 1010;
+        assert!(n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
 
-        let n = 1188511885;
-        assert!(n.repeats_sequence_of_digits_twice());
+        let n: u64 = 1188511885;
+        assert!(n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
 
-        let n = 10;
-        assert!(!n.repeats_sequence_of_digits_twice());
+        let n: u64 = 10;
+        assert!(!n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
 
-        let n = 644446;
-        assert!(!n.repeats_sequence_of_digits_twice());
+        let n: u64 = 644446;
+        assert!(!n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
     }
 
     #[test]
     fn u64_can_detect_repeated_digit_sequences_of_varying_lengths() {
-        let n = 11;
-        assert!(n.repeats_sequence_of_digits());
+        let n: u64 = 11;
+        assert!(n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+
+        let n: u64 = 22;
+        assert!(n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+
+        let n: u64 = 999;
+        assert!(n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+
+        let n: u64 = 824824824;
+        assert!(n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+
+        let n: u64 = 7;
+        assert!(!n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+
+        let n: u64 = 1010101;
+        assert!(!n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+    }
 
-        let n = 22;
-        assert!(n.repeats_sequence_of_digits());
+    #[test]
+    fn long_ids_can_detect_repeated_digit_sequences() {
+        assert!(11111111111u64.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(!11111111111u64.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
+        assert!(!12345678901234567891u64.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(12341234123412341234u64.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(1234567890_1234567890u64.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
+
+        assert!(123456789012345_123456789012345u128.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
+        assert!(123456_123456_123456_123456_123456u128.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(!123456_123456_123456_123456_123457u128.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(!u128::MAX.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+    }
 
-        let n = 999;
-        assert!(n.repeats_sequence_of_digits());
+    #[test]
+    fn repeated_digit_sequences_can_be_detected_in_any_radix() {
+        assert!(0xabab_u64.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 16));
+        assert!(!0xabab_u64.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
+        assert!(0xfff_u64.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 16));
+        assert!(!0xfff_u64.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 16));
+        assert!(0b1010_u8.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 2));
+        assert!(!0b10101_u8.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 2));
+        assert!(u64::from_str_radix("zzzz", 36).unwrap().repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 36));
+        assert!(u128::MAX.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 2));
+        assert!(u128::MAX.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 16));
+    }
 
-        let n = 824824824;
-        assert!(n.repeats_sequence_of_digits());
+    #[test]
+    fn product_id_ranges_can_be_parsed_in_any_radix() {
+        assert!(ProductIdRange::from_str_radix("ff-1A0", 16).is_ok_and(| r | r == ProductIdRange::new(255, 416)));
+        assert!(ProductIdRange::from_str_radix("10-zz", 36).is_ok_and(| r | r == ProductIdRange::new(36, 1295)));
+        assert!(ProductIdRange::from_str_radix("10-12", 2).is_err());
+    }
+
+    #[test]
+    fn invalid_id_totals_match_the_brute_force_reference_in_any_radix() {
+        for radix in [2, 3, 7, 16, 36] {
+            for r in [ProductIdRange::new(0, 5_000), ProductIdRange::new(46_000, 47_000), ProductIdRange::new(1_679_600, 1_679_700)] {
+                for policy in [RepetitionPolicy::TWICE, RepetitionPolicy::AT_LEAST_TWICE, RepetitionPolicy::Exactly(3), RepetitionPolicy::UnitLength { min: 2, max: 4 }] {
+                    assert_eq!(r.invalid_id_total(policy, radix), r.brute_force_invalid_id_total(policy, radix), "{:?} {:?} {}", r, policy, radix);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_width_can_detect_repeated_digit_sequences() {
+        assert!(99u8.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
+        assert!(!255u8.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(6464u16.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
+        assert!(22222u16.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(123123u32.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10));
+        assert!(1111111usize.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert!(!0u32.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10));
     }
 
     #[test]
@@ -232,7 +722,7 @@ mod tests {
             .map(| s | ProductIdRange::from_str(s))
             .filter_map(| r | r.ok())
             .flat_map(| r | r.into_iter())
-            .filter(| p | p.repeats_sequence_of_digits_twice())
+            .filter(| p | p.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10))
             .sum::<u64>();
 
         assert_eq!(1227775554, result);
@@ -258,9 +748,104 @@ mod tests {
             .map(| s | ProductIdRange::from_str(s))
             .filter_map(| r | r.ok())
             .flat_map(| r | r.into_iter())
-            .filter(| p | p.repeats_sequence_of_digits())
+            .filter(| p | p.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10))
             .sum::<u64>();
 
         assert_eq!(4174379265, result);
     }
-}
\ No newline at end of file
+
+    fn example_ranges() -> Vec<ProductIdRange> {
+        "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124"
+            .split(',')
+            .filter_map(| s | ProductIdRange::from_str(s).ok())
+            .collect()
+    }
+
+    #[test]
+    fn invalid_ids_can_be_totalled_without_enumerating_ranges() {
+        let twice = example_ranges().iter()
+            .fold(InvalidIdTotal::default(), | total, r | total + r.invalid_id_total(RepetitionPolicy::TWICE, 10));
+        assert_eq!(twice, InvalidIdTotal { count: 8, sum: 1227775554 });
+
+        let at_least_twice = example_ranges().iter()
+            .fold(InvalidIdTotal::default(), | total, r | total + r.invalid_id_total(RepetitionPolicy::AT_LEAST_TWICE, 10));
+        assert_eq!(at_least_twice, InvalidIdTotal { count: 13, sum: 4174379265 });
+    }
+
+    #[test]
+    fn invalid_id_totals_match_the_brute_force_reference() {
+        let mut ranges = example_ranges();
+        ranges.extend([
+            ProductIdRange::new(0, 0),
+            ProductIdRange::new(1, 9),
+            ProductIdRange::new(1, 20_000),
+            ProductIdRange::new(999_990, 1_001_100),
+            ProductIdRange::new(11_111_100, 11_111_200),
+            ProductIdRange::new(9_999_999_990, 10_000_000_100),
+        ]);
+
+        for r in ranges {
+            for policy in [RepetitionPolicy::TWICE, RepetitionPolicy::AT_LEAST_TWICE, RepetitionPolicy::Exactly(3), RepetitionPolicy::UnitLength { min: 2, max: 4 }] {
+                assert_eq!(r.invalid_id_total(policy, 10), r.brute_force_invalid_id_total(policy, 10), "{:?} {:?}", r, policy);
+            }
+        }
+    }
+
+    #[test]
+    fn options_can_be_parsed() {
+        let args = | s: &str | s.split_whitespace().map(String::from).collect::<Vec<String>>();
+
+        assert_eq!(Options::parse(&args("data/example.txt")), Ok(Options {
+            file_path: Some("data/example.txt".to_string()),
+            brute_force: false,
+            radix: 10,
+            report_format: None,
+            policy: None,
+            threads: None,
+            generate_bound: None,
+            output_path: None,
+        }));
+        assert_eq!(
+            Options::parse(&args("--brute-force data/example.txt --radix 16 --report json --policy exactly:3 --threads 32")),
+            Ok(Options {
+                file_path: Some("data/example.txt".to_string()),
+                brute_force: true,
+                radix: 16,
+                report_format: Some(ReportFormat::Json),
+                policy: Some(RepetitionPolicy::Exactly(3)),
+                threads: Some(32),
+                generate_bound: None,
+                output_path: None,
+            })
+        );
+        assert!(Options::parse(&args("--report csv data/example.txt")).is_err());
+        assert!(Options::parse(&args("--policy thrice data/example.txt")).is_err());
+        assert!(Options::parse(&args("--threads 0 data/example.txt")).is_err());
+        assert!(Options::parse(&args("--threads 1024 data/example.txt")).is_ok_and(| o | o.threads == Some(1024)));
+        assert!(Options::parse(&args("--threads 100000000000 data/example.txt")).is_err());
+
+        let options = Options::parse(&args("--generate ff --output ids.txt --radix 16"));
+        assert!(options.is_ok_and(| o | o.generate_bound == Some(255) && o.output_path == Some("ids.txt".to_string()) && o.file_path.is_none()));
+        assert!(Options::parse(&args("--generate 100 data/example.txt")).is_err());
+        assert!(Options::parse(&args("--generate ff")).is_err());
+        assert!(Options::parse(&args("--output ids.txt data/example.txt")).is_err());
+        assert!(Options::parse(&args("")).is_err());
+        assert!(Options::parse(&args("--radix 37 data/example.txt")).is_err());
+        assert!(Options::parse(&args("data/example.txt --radix")).is_err());
+        assert!(Options::parse(&args("--fast data/example.txt")).is_err());
+    }
+
+    #[test]
+    fn invalid_ids_can_be_generated() {
+        let mut output = Vec::new();
+        generate(&mut output, 120, RepetitionPolicy::AT_LEAST_TWICE, 10).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "11\n22\n33\n44\n55\n66\n77\n88\n99\n111\n");
+
+        let mut output = Vec::new();
+        generate(&mut output, 0xff, RepetitionPolicy::TWICE, 16).unwrap();
+        let ids: Vec<String> = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+        assert_eq!(ids.len(), 15);
+        assert_eq!(ids.first().map(String::as_str), Some("11"));
+        assert_eq!(ids.last().map(String::as_str), Some("ff"));
+    }
+}
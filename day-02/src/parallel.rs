@@ -0,0 +1,116 @@
+use std::mem;
+use std::thread;
+
+use crate::ProductIdRange;
+use crate::invalid_ids::InvalidIdTotal;
+
+/// Splits `ranges` into at most `chunks` groups holding about the same number of ids, splitting
+/// ranges between groups where needed. Every id in `ranges` ends up in exactly one group, and there
+/// are never more groups than ids.
+pub fn balanced_chunks(ranges: &[ProductIdRange], chunks: usize) -> Vec<Vec<ProductIdRange>> {
+    let ids: u128 = ranges.iter().map(|r| (r.to - r.from) as u128 + 1).sum();
+    let chunks = (chunks.max(1) as u128).min(ids);
+    let per_chunk = ids.div_ceil(chunks.max(1)).max(1);
+
+    let mut balanced = Vec::with_capacity(chunks as usize);
+    let mut chunk = Vec::new();
+    let mut room = per_chunk;
+    for r in ranges {
+        let mut ids = r.iter();
+        while let Some(taken) = ids.take_range(room) {
+            room -= (taken.to - taken.from) as u128 + 1;
+            chunk.push(taken);
+
+            if room == 0 {
+                balanced.push(mem::take(&mut chunk));
+                room = per_chunk;
+            }
+        }
+    }
+
+    if !chunk.is_empty() {
+        balanced.push(chunk);
+    }
+
+    balanced
+}
+
+/// Totals the invalid ids in `ranges` across `threads` threads, giving each a balanced share of the
+/// ids to `evaluate`. The totals are only ever added together, so the result is the same however
+/// the work is split.
+pub fn invalid_id_total<F>(ranges: &[ProductIdRange], threads: usize, evaluate: F) -> InvalidIdTotal
+where
+    F: Fn(&ProductIdRange) -> InvalidIdTotal + Sync,
+{
+    let chunks = balanced_chunks(ranges, threads);
+    let evaluate = &evaluate;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = chunks.iter()
+            .map(|chunk| scope.spawn(move || chunk.iter().map(evaluate).fold(InvalidIdTotal::default(), |total, r| total + r)))
+            .collect();
+
+        handles.into_iter()
+            .map(|handle| handle.join().expect("a thread totalling invalid ids panicked"))
+            .fold(InvalidIdTotal::default(), |total, chunk_total| total + chunk_total)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::RepetitionPolicy;
+
+    fn ranges() -> Vec<ProductIdRange> {
+        vec![ProductIdRange::new(11, 22), ProductIdRange::new(95, 115), ProductIdRange::new(998, 1012), ProductIdRange::new(200_000, 260_000)]
+    }
+
+    #[test]
+    fn chunks_are_balanced_and_cover_every_id() {
+        let ranges = ranges();
+        for chunks in 1..=8 {
+            let balanced = balanced_chunks(&ranges, chunks);
+            assert!(balanced.len() <= chunks);
+
+            let sizes: Vec<u64> = balanced.iter().map(|chunk| chunk.iter().map(|r| r.to - r.from + 1).sum()).collect();
+            assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= sizes.len() as u64, "{:?}", sizes);
+
+            let ids: Vec<u64> = balanced.iter().flatten().flat_map(|r| r.iter()).collect();
+            let expected: Vec<u64> = ranges.iter().flat_map(|r| r.iter()).collect();
+            assert_eq!(ids, expected);
+        }
+    }
+
+    #[test]
+    fn chunks_can_reach_the_largest_id() {
+        let balanced = balanced_chunks(&[ProductIdRange::new(0, u64::MAX)], 3);
+        assert_eq!(balanced.len(), 3);
+        assert_eq!(balanced[0][0].from, 0);
+        assert_eq!(balanced[2][0].to, u64::MAX);
+        assert_eq!(balanced[1][0].from, balanced[0][0].to + 1);
+    }
+
+    #[test]
+    fn more_threads_than_ids_can_be_used() {
+        let balanced = balanced_chunks(&[ProductIdRange::new(5, 6)], 4);
+        assert_eq!(balanced, vec![vec![ProductIdRange::new(5, 5)], vec![ProductIdRange::new(6, 6)]]);
+        assert!(balanced_chunks(&[], 4).is_empty());
+
+        let balanced = balanced_chunks(&[ProductIdRange::new(5, 6)], usize::MAX);
+        assert_eq!(balanced, vec![vec![ProductIdRange::new(5, 5)], vec![ProductIdRange::new(6, 6)]]);
+    }
+
+    #[test]
+    fn parallel_totals_match_the_sequential_totals() {
+        let ranges = ranges();
+        for policy in [RepetitionPolicy::TWICE, RepetitionPolicy::AT_LEAST_TWICE] {
+            let sequential = ranges.iter()
+                .fold(InvalidIdTotal::default(), |total, r| total + r.brute_force_invalid_id_total(policy, 10));
+
+            for threads in [1, 2, 3, 7, 32] {
+                assert_eq!(invalid_id_total(&ranges, threads, |r| r.brute_force_invalid_id_total(policy, 10)), sequential);
+                assert_eq!(invalid_id_total(&ranges, threads, |r| r.invalid_id_total(policy, 10)), sequential);
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Which repetitions of a sequence of digits make a product id invalid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepetitionPolicy {
+    /// The id is some sequence of digits repeated exactly this many times, such as `6464` for 2.
+    Exactly(u32),
+    /// The id is some sequence of digits repeated this many times or more, such as `824824824` for 2.
+    AtLeast(u32),
+    /// The id is some sequence of digits repeated two or more times, and that sequence is between
+    /// `min` and `max` digits long (inclusive).
+    UnitLength { min: u32, max: u32 },
+}
+
+impl RepetitionPolicy {
+    /// The rule for the first part of the puzzle, such as `6464`.
+    pub const TWICE: Self = RepetitionPolicy::Exactly(2);
+    /// The rule for the second part of the puzzle, such as `824824824`.
+    pub const AT_LEAST_TWICE: Self = RepetitionPolicy::AtLeast(2);
+
+    fn accepts(&self, unit_length: u32, repetitions: u32) -> bool {
+        match *self {
+            RepetitionPolicy::Exactly(times) => repetitions == times,
+            RepetitionPolicy::AtLeast(times) => repetitions >= times,
+            RepetitionPolicy::UnitLength { min, max } => (min..=max).contains(&unit_length),
+        }
+    }
+
+    /// Whether an id made of its shortest repeating sequence of `unit_length` digits, repeated
+    /// `repetitions` times, is invalid. An id such as `111111` can also be read as a longer sequence
+    /// repeated fewer times, `11` three times or `111` twice, so each of those readings is checked.
+    pub fn matches(&self, unit_length: u32, repetitions: u32) -> bool {
+        (1..repetitions)
+            .filter(|combined| repetitions.is_multiple_of(*combined))
+            .any(|combined| self.accepts(unit_length * combined, repetitions / combined))
+    }
+}
+
+impl FromStr for RepetitionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!(
+            "Unknown repetition policy {:?}, expected \"twice\", \"at-least-twice\", \"exactly:<times>\", \"at-least:<times>\" or \"unit-length:<min>-<max>\".",
+            s
+        );
+        let times = |value: &str| value.parse::<u32>().ok().filter(|&times| times >= 2).ok_or_else(error);
+
+        match s.split_once(':') {
+            None if s == "twice" => Ok(RepetitionPolicy::TWICE),
+            None if s == "at-least-twice" => Ok(RepetitionPolicy::AT_LEAST_TWICE),
+            Some(("exactly", value)) => Ok(RepetitionPolicy::Exactly(times(value)?)),
+            Some(("at-least", value)) => Ok(RepetitionPolicy::AtLeast(times(value)?)),
+            Some(("unit-length", value)) => {
+                let (min, max) = value.split_once('-').unwrap_or((value, value));
+                let min = min.parse::<u32>().map_err(|_| error())?;
+                let max = max.parse::<u32>().map_err(|_| error())?;
+                match 1 <= min && min <= max {
+                    true => Ok(RepetitionPolicy::UnitLength { min, max }),
+                    false => Err(error()),
+                }
+            },
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Describes the ids the policy finds, following "repeating".
+impl fmt::Display for RepetitionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RepetitionPolicy::Exactly(2) => write!(f, "a sequence of digits twice"),
+            RepetitionPolicy::Exactly(times) => write!(f, "a sequence of digits {} times", times),
+            RepetitionPolicy::AtLeast(2) => write!(f, "a sequence of digits at least twice"),
+            RepetitionPolicy::AtLeast(times) => write!(f, "a sequence of digits at least {} times", times),
+            RepetitionPolicy::UnitLength { min, max } if min == max => write!(f, "a sequence of {} digits", min),
+            RepetitionPolicy::UnitLength { min, max } => write!(f, "a sequence of {} to {} digits", min, max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_match_the_puzzle_rules() {
+        // 6464, 111 and 111111.
+        assert!(RepetitionPolicy::TWICE.matches(2, 2));
+        assert!(!RepetitionPolicy::TWICE.matches(1, 3));
+        assert!(RepetitionPolicy::TWICE.matches(1, 6));
+
+        assert!(RepetitionPolicy::AT_LEAST_TWICE.matches(2, 2));
+        assert!(RepetitionPolicy::AT_LEAST_TWICE.matches(1, 3));
+        assert!(!RepetitionPolicy::AT_LEAST_TWICE.matches(7, 1));
+    }
+
+    #[test]
+    fn every_reading_of_an_id_is_checked() {
+        // 111111 is 1 six times, 11 three times and 111 twice.
+        assert!(RepetitionPolicy::Exactly(3).matches(1, 6));
+        assert!(RepetitionPolicy::Exactly(6).matches(1, 6));
+        assert!(!RepetitionPolicy::Exactly(4).matches(1, 6));
+        assert!(RepetitionPolicy::AtLeast(6).matches(1, 6));
+        assert!(!RepetitionPolicy::AtLeast(7).matches(1, 6));
+        assert!(RepetitionPolicy::UnitLength { min: 3, max: 5 }.matches(1, 6));
+        assert!(!RepetitionPolicy::UnitLength { min: 4, max: 5 }.matches(1, 6));
+        assert!(!RepetitionPolicy::UnitLength { min: 6, max: 6 }.matches(1, 6));
+    }
+
+    #[test]
+    fn policies_can_be_parsed() {
+        assert_eq!(RepetitionPolicy::from_str("twice"), Ok(RepetitionPolicy::TWICE));
+        assert_eq!(RepetitionPolicy::from_str("at-least-twice"), Ok(RepetitionPolicy::AT_LEAST_TWICE));
+        assert_eq!(RepetitionPolicy::from_str("exactly:3"), Ok(RepetitionPolicy::Exactly(3)));
+        assert_eq!(RepetitionPolicy::from_str("at-least:4"), Ok(RepetitionPolicy::AtLeast(4)));
+        assert_eq!(RepetitionPolicy::from_str("unit-length:2-4"), Ok(RepetitionPolicy::UnitLength { min: 2, max: 4 }));
+        assert_eq!(RepetitionPolicy::from_str("unit-length:3"), Ok(RepetitionPolicy::UnitLength { min: 3, max: 3 }));
+
+        for s in ["thrice", "exactly:1", "at-least:x", "unit-length:4-2", "unit-length:0-2", "exactly"] {
+            assert!(RepetitionPolicy::from_str(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn policies_can_be_described() {
+        assert_eq!(RepetitionPolicy::TWICE.to_string(), "a sequence of digits twice");
+        assert_eq!(RepetitionPolicy::AtLeast(3).to_string(), "a sequence of digits at least 3 times");
+        assert_eq!(RepetitionPolicy::UnitLength { min: 2, max: 4 }.to_string(), "a sequence of 2 to 4 digits");
+    }
+}
@@ -0,0 +1,249 @@
+//! Property tests for the repeated digit sequence detector. Cases are drawn from a small seeded
+//! generator so that every run checks the same cases, and a failing case is shrunk to the smallest
+//! counterexample that still fails before it is reported.
+
+use std::fmt::Debug;
+
+use crate::policy::RepetitionPolicy;
+use crate::{ProductIdRange, RepeatedDigitSequenceDetector, format_in_radix};
+
+const SEED: u64 = 0x5EED_0002;
+const CASES: usize = 500;
+
+/// A SplitMix64 pseudo-random number generator.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number from `low` to `high` (inclusive). The slight bias towards smaller numbers doesn't
+    /// matter for tests.
+    fn between(&mut self, low: u64, high: u64) -> u64 {
+        match (high - low).checked_add(1) {
+            Some(span) => low + self.next_u64() % span,
+            None => self.next_u64(),
+        }
+    }
+
+    /// A number of a random number of digits, so that short numbers are as likely as long ones.
+    fn number(&mut self) -> u64 {
+        let digits = self.between(1, 20) as u32;
+        match 10u64.checked_pow(digits) {
+            Some(limit) => self.between(0, limit - 1),
+            None => self.next_u64(),
+        }
+    }
+}
+
+/// Checks `property` against `cases` values from `generate`. Returns the first value found not to
+/// have the property, after shrinking it for as long as a smaller value from `shrink` still fails.
+fn falsify<T: Clone>(
+    cases: usize,
+    generate: impl Fn(&mut Rng) -> T,
+    shrink: impl Fn(&T) -> Vec<T>,
+    property: impl Fn(&T) -> bool,
+) -> Option<T> {
+    let mut rng = Rng(SEED);
+    let mut counterexample = (0..cases).map(|_| generate(&mut rng)).find(|value| !property(value))?;
+
+    while let Some(smaller) = shrink(&counterexample).into_iter().find(|value| !property(value)) {
+        counterexample = smaller;
+    }
+
+    Some(counterexample)
+}
+
+fn check<T: Clone + Debug>(generate: impl Fn(&mut Rng) -> T, shrink: impl Fn(&T) -> Vec<T>, property: impl Fn(&T) -> bool) {
+    if let Some(counterexample) = falsify(CASES, generate, shrink, property) {
+        panic!("property failed for {:?} (seed {:#x})", counterexample, SEED);
+    }
+}
+
+fn shrink_number(n: &u64) -> Vec<u64> {
+    let n = *n;
+    [0, n / 10, n / 2, n.saturating_sub(1)].into_iter().filter(|&smaller| smaller < n).collect()
+}
+
+/// A sequence of digits in some radix, repeated to make a number.
+#[derive(Debug, Clone)]
+struct Repetition {
+    unit: u128,
+    unit_length: u32,
+    times: u32,
+    radix: u32,
+}
+
+impl Repetition {
+    /// The repeated number, or `None` if it is too large for a u128.
+    fn value(&self) -> Option<u128> {
+        let shift = (self.radix as u128).checked_pow(self.unit_length)?;
+        (0..self.times).try_fold(0u128, |value, _| value.checked_mul(shift)?.checked_add(self.unit))
+    }
+
+    fn generate(rng: &mut Rng) -> Self {
+        loop {
+            let radix = rng.between(2, 36) as u32;
+            let unit_length = rng.between(1, 12) as u32;
+            let times = rng.between(2, 8) as u32;
+
+            // The first digit can't be 0, as ids have no leading zeroes.
+            let mut unit = rng.between(1, radix as u64 - 1) as u128;
+            for _ in 1..unit_length {
+                unit = unit * radix as u128 + rng.between(0, radix as u64 - 1) as u128;
+            }
+
+            let repetition = Self { unit, unit_length, times, radix };
+            if repetition.value().is_some() {
+                return repetition;
+            }
+        }
+    }
+
+    fn shrink(&self) -> Vec<Self> {
+        let mut smaller = Vec::new();
+        if self.times > 2 {
+            smaller.push(Self { times: self.times - 1, ..self.clone() });
+        }
+        if self.unit_length > 1 {
+            smaller.push(Self { unit: self.unit / self.radix as u128, unit_length: self.unit_length - 1, ..self.clone() });
+        }
+        if self.radix != 10 && self.unit < 10u128.pow(self.unit_length) && self.unit >= 10u128.pow(self.unit_length - 1) {
+            smaller.push(Self { radix: 10, ..self.clone() });
+        }
+        smaller
+    }
+}
+
+/// An independent check for a sequence of digits repeated at least twice: such a string is found
+/// inside itself doubled, other than at the very start or end.
+fn independently_repeats(digits: &str) -> bool {
+    let doubled = format!("{0}{0}", digits);
+    doubled[1..doubled.len() - 1].contains(digits)
+}
+
+fn independently_repeats_twice(digits: &str) -> bool {
+    let half = &digits[..digits.len() / 2];
+    format!("{0}{0}", half) == digits
+}
+
+fn ranges(rng: &mut Rng) -> (ProductIdRange, u32) {
+    let radix = match rng.between(0, 3) {
+        0 => rng.between(2, 36) as u32,
+        _ => 10,
+    };
+
+    // Centre most ranges on an invalid id, as ranges picked at random rarely contain one.
+    let centre = match rng.between(0, 3) {
+        0 => rng.number(),
+        _ => {
+            let repetition = Repetition::generate(rng);
+            let repetition = Repetition { radix, unit: repetition.unit % (radix as u128).pow(repetition.unit_length), ..repetition };
+            repetition.value().and_then(|value| u64::try_from(value).ok()).unwrap_or_else(|| rng.number())
+        },
+    };
+
+    let from = centre.saturating_sub(rng.between(0, 1_000));
+    let to = centre.saturating_add(rng.between(0, 1_000));
+    (ProductIdRange::new(from, to), radix)
+}
+
+fn shrink_range((r, radix): &(ProductIdRange, u32)) -> Vec<(ProductIdRange, u32)> {
+    let (from, to) = (r.from, r.to);
+    let middle = from + (to - from) / 2;
+    let mut smaller = vec![(ProductIdRange::new(from, middle), *radix)];
+    if middle < to {
+        smaller.push((ProductIdRange::new(middle + 1, to), *radix));
+    }
+    if from < to {
+        smaller.push((ProductIdRange::new(from + 1, to), *radix));
+        smaller.push((ProductIdRange::new(from, to - 1), *radix));
+    }
+    smaller
+}
+
+#[test]
+fn random_numbers_can_be_generated_repeatably() {
+    let first: Vec<u64> = (0..5).scan(Rng(1), |rng, _| Some(rng.next_u64())).collect();
+    let second: Vec<u64> = (0..5).scan(Rng(1), |rng, _| Some(rng.next_u64())).collect();
+    assert_eq!(first, second);
+    assert!(first.windows(2).all(|w| w[0] != w[1]));
+
+    let mut rng = Rng(SEED);
+    assert!((0..1_000).all(|_| (3..=7).contains(&rng.between(3, 7))));
+    assert!((0..1_000).all(|_| rng.between(u64::MAX, u64::MAX) == u64::MAX));
+}
+
+#[test]
+fn failing_cases_are_shrunk_to_a_minimal_counterexample() {
+    let counterexample = falsify(CASES, |rng| rng.number(), shrink_number, |&n| n < 1_000);
+    assert_eq!(counterexample, Some(1_000));
+
+    let counterexample = falsify(CASES, Repetition::generate, Repetition::shrink, |r| r.times < 3);
+    assert!(counterexample.is_some_and(|r| r.times == 3 && r.unit_length == 1 && r.radix == 10));
+
+    assert_eq!(falsify(CASES, |rng| rng.number(), shrink_number, |_| true), None);
+}
+
+#[test]
+fn ids_repeated_twice_are_repeated_at_least_twice() {
+    check(
+        |rng| rng.number(),
+        shrink_number,
+        |&n| !n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10) || n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10),
+    );
+
+    check(
+        |rng| (rng.number(), rng.between(2, 36) as u32),
+        |&(n, radix)| shrink_number(&n).into_iter().map(|n| (n, radix)).collect(),
+        |&(n, radix)| !n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, radix) || n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, radix),
+    );
+}
+
+#[test]
+fn repeated_sequences_are_detected() {
+    check(Repetition::generate, Repetition::shrink, |r| {
+        let value = r.value().unwrap();
+        value.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, r.radix)
+            && value.repeats_sequence_of_digits_under(RepetitionPolicy::Exactly(r.times), r.radix)
+            && value.repeats_sequence_of_digits_under(RepetitionPolicy::UnitLength { min: r.unit_length, max: r.unit_length }, r.radix)
+            && (r.times != 2 || value.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, r.radix))
+    });
+}
+
+#[test]
+fn the_detector_agrees_with_an_independent_check() {
+    check(
+        |rng| rng.number(),
+        shrink_number,
+        |&n| {
+            let digits = n.to_string();
+            n.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, 10) == independently_repeats(&digits)
+                && n.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, 10) == independently_repeats_twice(&digits)
+        },
+    );
+}
+
+#[test]
+fn range_sums_agree_with_an_independent_implementation() {
+    check(ranges, shrink_range, |(r, radix)| {
+        let independent = |repeats: fn(&str) -> bool| -> (u64, u128) {
+            r.iter()
+                .filter(|&id| repeats(&format_in_radix(id, *radix)))
+                .fold((0, 0), |(count, sum), id| (count + 1, sum + id as u128))
+        };
+
+        [(RepetitionPolicy::TWICE, independent(independently_repeats_twice)), (RepetitionPolicy::AT_LEAST_TWICE, independent(independently_repeats))]
+            .into_iter()
+            .all(|(policy, (count, sum))| {
+                let brute_force = r.brute_force_invalid_id_total(policy, *radix);
+                let arithmetic = r.invalid_id_total(policy, *radix);
+                brute_force.count == count && brute_force.sum == sum && arithmetic == brute_force
+            })
+    });
+}
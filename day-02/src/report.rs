@@ -0,0 +1,235 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::invalid_ids::{self, InvalidId, InvalidIdTotal};
+use crate::policy::RepetitionPolicy;
+use crate::{ProductIdRange, format_in_radix};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(ReportFormat::Table),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!("Unknown report format {:?}, expected \"table\" or \"json\".", s)),
+        }
+    }
+}
+
+/// The ids in a single range that are invalid under some policy, with subtotals for each of the
+/// puzzle's rules. The subtotals cover every id in the range that breaks the rule, whether or not
+/// it is invalid under the policy. The ids themselves are only worked out as they are written, so a
+/// wide range never has to be held in memory.
+#[derive(Debug, PartialEq)]
+pub struct RangeReport {
+    pub range: ProductIdRange,
+    pub policy: RepetitionPolicy,
+    pub radix: u32,
+    pub twice: InvalidIdTotal,
+    pub at_least_twice: InvalidIdTotal,
+}
+
+impl RangeReport {
+    pub fn new(range: ProductIdRange, policy: RepetitionPolicy, radix: u32) -> Self {
+        let twice = invalid_ids::invalid_id_total(range.from, range.to, RepetitionPolicy::TWICE, radix);
+        let at_least_twice = invalid_ids::invalid_id_total(range.from, range.to, RepetitionPolicy::AT_LEAST_TWICE, radix);
+
+        Self { range, policy, radix, twice, at_least_twice }
+    }
+
+    /// The ids in the range that are invalid under the policy, in ascending order.
+    pub fn ids(&self) -> impl Iterator<Item = InvalidId> + use<> {
+        let policy = self.policy;
+        invalid_ids::invalid_ids(self.range.from, self.range.to, self.radix)
+            .filter(move |invalid_id| invalid_id.is_invalid_under(policy))
+    }
+}
+
+/// Which of the rules an id is invalid under. Every id repeating a sequence twice also repeats it
+/// at least twice, so no id is invalid under the first rule alone.
+fn rules(invalid_id: &InvalidId) -> &'static str {
+    match invalid_id.is_invalid_under(RepetitionPolicy::TWICE) {
+        true => "both",
+        false => "part 2",
+    }
+}
+
+/// Writes out every invalid id in `reports`, followed by the totals across all of the ranges. Ids,
+/// ranges and units are shown in `radix` in the table, while sums are always decimal. The JSON report
+/// gives ids, ranges and sums as decimal numbers, and units as strings of digits in `radix`.
+pub fn write_report<W: Write>(mut writer: W, reports: &[RangeReport], radix: u32, format: ReportFormat) -> io::Result<()> {
+    let twice = reports.iter().fold(InvalidIdTotal::default(), |total, report| total + report.twice);
+    let at_least_twice = reports.iter().fold(InvalidIdTotal::default(), |total, report| total + report.at_least_twice);
+
+    match format {
+        ReportFormat::Table => {
+            // The columns are sized in a first pass over the ids, which are then worked out again to
+            // write them rather than being kept in between.
+            let (id_width, unit_width) = reports.iter()
+                .flat_map(RangeReport::ids)
+                .fold(("Id".len(), "Unit".len()), |(id_width, unit_width), invalid_id| (
+                    id_width.max(format_in_radix(invalid_id.id, radix).len()),
+                    unit_width.max(format_in_radix(invalid_id.unit, radix).len()),
+                ));
+
+            for report in reports {
+                writeln!(writer, "Range {}-{}", format_in_radix(report.range.from, radix), format_in_radix(report.range.to, radix))?;
+                let mut ids = report.ids().peekable();
+                if ids.peek().is_some() {
+                    writeln!(writer, "  {:<id_width$}  {:<unit_width$}  Repetitions  Rules", "Id", "Unit")?;
+                }
+                for invalid_id in ids {
+                    writeln!(
+                        writer,
+                        "  {:<id_width$}  {:<unit_width$}  {:<11}  {}",
+                        format_in_radix(invalid_id.id, radix), format_in_radix(invalid_id.unit, radix), invalid_id.repetitions, rules(&invalid_id)
+                    )?;
+                }
+                writeln!(writer, "  Subtotal: {}", describe(report.twice, report.at_least_twice))?;
+                writeln!(writer)?;
+            }
+
+            writeln!(writer, "Total: {}", describe(twice, at_least_twice))
+        },
+        ReportFormat::Json => {
+            write!(writer, "{{\"ranges\":[")?;
+            for (i, report) in reports.iter().enumerate() {
+                if i > 0 {
+                    write!(writer, ",")?;
+                }
+
+                write!(writer, "{{\"from\":{},\"to\":{},\"invalid_ids\":[", report.range.from, report.range.to)?;
+                for (j, invalid_id) in report.ids().enumerate() {
+                    if j > 0 {
+                        write!(writer, ",")?;
+                    }
+
+                    write!(
+                        writer,
+                        "{{\"id\":{},\"unit\":{},\"repetitions\":{},\"rules\":{}}}",
+                        invalid_id.id, json_string(&format_in_radix(invalid_id.unit, radix)), invalid_id.repetitions, json_string(rules(&invalid_id))
+                    )?;
+                }
+                write!(writer, "],{}}}", json_totals(report.twice, report.at_least_twice))?;
+            }
+            writeln!(writer, "],{}}}", json_totals(twice, at_least_twice))
+        },
+    }
+}
+
+/// `s` as a JSON string, in quotes and with any quotes, backslashes and control characters escaped.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn describe(twice: InvalidIdTotal, at_least_twice: InvalidIdTotal) -> String {
+    format!(
+        "{} ids repeating twice with a sum of {}, {} ids repeating at least twice with a sum of {}.",
+        twice.count, twice.sum, at_least_twice.count, at_least_twice.sum
+    )
+}
+
+fn json_totals(twice: InvalidIdTotal, at_least_twice: InvalidIdTotal) -> String {
+    format!(
+        "\"twice\":{{\"count\":{},\"sum\":{}}},\"at_least_twice\":{{\"count\":{},\"sum\":{}}}",
+        twice.count, twice.sum, at_least_twice.count, at_least_twice.sum
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(ranges: &[ProductIdRange], radix: u32, format: ReportFormat) -> String {
+        let reports: Vec<RangeReport> = ranges.iter().map(|&range| RangeReport::new(range, RepetitionPolicy::AT_LEAST_TWICE, radix)).collect();
+        let mut output = Vec::new();
+        write_report(&mut output, &reports, radix, format).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn reports_can_be_written_as_a_table() {
+        let output = report(&[ProductIdRange::new(95, 115), ProductIdRange::new(824824821, 824824827), ProductIdRange::new(1698522, 1698528)], 10, ReportFormat::Table);
+        assert_eq!(output, "\
+Range 95-115
+  Id         Unit  Repetitions  Rules
+  99         9     2            both
+  111        1     3            part 2
+  Subtotal: 1 ids repeating twice with a sum of 99, 2 ids repeating at least twice with a sum of 210.
+
+Range 824824821-824824827
+  Id         Unit  Repetitions  Rules
+  824824824  824   3            part 2
+  Subtotal: 0 ids repeating twice with a sum of 0, 1 ids repeating at least twice with a sum of 824824824.
+
+Range 1698522-1698528
+  Subtotal: 0 ids repeating twice with a sum of 0, 0 ids repeating at least twice with a sum of 0.
+
+Total: 1 ids repeating twice with a sum of 99, 3 ids repeating at least twice with a sum of 824825034.
+");
+    }
+
+    #[test]
+    fn reports_can_be_written_as_json() {
+        let output = report(&[ProductIdRange::new(0x11, 0x22), ProductIdRange::new(0xaaa0, 0xabac)], 16, ReportFormat::Json);
+        assert_eq!(output, concat!(
+            "{\"ranges\":[",
+            "{\"from\":17,\"to\":34,\"invalid_ids\":[{\"id\":17,\"unit\":\"1\",\"repetitions\":2,\"rules\":\"both\"},{\"id\":34,\"unit\":\"2\",\"repetitions\":2,\"rules\":\"both\"}],",
+            "\"twice\":{\"count\":2,\"sum\":51},\"at_least_twice\":{\"count\":2,\"sum\":51}},",
+            "{\"from\":43680,\"to\":43948,\"invalid_ids\":[{\"id\":43690,\"unit\":\"a\",\"repetitions\":4,\"rules\":\"both\"},{\"id\":43947,\"unit\":\"ab\",\"repetitions\":2,\"rules\":\"both\"}],",
+            "\"twice\":{\"count\":2,\"sum\":87637},\"at_least_twice\":{\"count\":2,\"sum\":87637}}",
+            "],\"twice\":{\"count\":4,\"sum\":87688},\"at_least_twice\":{\"count\":4,\"sum\":87688}}\n",
+        ));
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("ab"), "\"ab\"");
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), "\"a\\\"b\\\\c\\nd\\u0001\"");
+    }
+
+    #[test]
+    fn reports_agree_with_the_detector() {
+        use crate::RepeatedDigitSequenceDetector;
+
+        for radix in [2, 10, 36] {
+            let report = RangeReport::new(ProductIdRange::new(1, 100_000), RepetitionPolicy::AT_LEAST_TWICE, radix);
+            let brute_force: Vec<u64> = (1..=100_000u64).filter(|id| id.repeats_sequence_of_digits_under(RepetitionPolicy::AT_LEAST_TWICE, radix)).collect();
+            assert_eq!(report.ids().map(|invalid_id| invalid_id.id).collect::<Vec<u64>>(), brute_force);
+
+            for invalid_id in report.ids() {
+                assert_eq!(invalid_id.is_invalid_under(RepetitionPolicy::TWICE), invalid_id.id.repeats_sequence_of_digits_under(RepetitionPolicy::TWICE, radix));
+            }
+        }
+    }
+
+    #[test]
+    fn reports_only_list_ids_invalid_under_the_policy() {
+        let report = RangeReport::new(ProductIdRange::new(111110, 121212), RepetitionPolicy::Exactly(3), 10);
+        assert_eq!(report.ids().map(|invalid_id| invalid_id.id).collect::<Vec<u64>>(), vec![111111, 121212]);
+
+        // The subtotals for each rule still cover the whole range, from 111111 up to 121121.
+        let twice_sum = (111..=121).map(|unit| unit * 1001).sum::<u128>();
+        assert_eq!(report.twice, InvalidIdTotal { count: 11, sum: twice_sum });
+        assert_eq!(report.at_least_twice, InvalidIdTotal { count: 12, sum: twice_sum + 121212 });
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use crate::Selection;
+
+/// Safety rules on which batteries in a bank may be turned on together.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Constraints {
+    /// No two neighbouring batteries may both be turned on.
+    pub no_adjacent: bool,
+    /// At least one battery must be turned on in every segment of this many batteries, counted from
+    /// the start of the bank. The last segment may be shorter.
+    pub segment: Option<usize>,
+    pub window: Option<Window>,
+}
+
+/// At most `limit` batteries may be turned on in any `width` neighbouring batteries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub limit: usize,
+    pub width: usize,
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    /// Parses a window such as `3/10`, for at most 3 batteries in any 10.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid window {:?}, expected a limit and a width of at least 1 such as \"3/10\".", s);
+        let (limit, width) = s.split_once('/').ok_or_else(error)?;
+        let parse = | n: &str | n.parse::<usize>().ok().filter(| &n | n > 0).ok_or_else(error);
+        Ok(Window { limit: parse(limit)?, width: parse(width)? })
+    }
+}
+
+/// A bound too loose to ever matter, which can still be added to another without overflowing.
+const UNBOUNDED: i64 = i64::MAX / 4;
+
+/// The constraints as bounds on how many batteries are turned on before each position in a bank,
+/// where every bound between two positions is no further apart than `band`. The bounds are tightened
+/// from the end of the bank back to its start, so that working forwards, any choice that keeps to the
+/// bounds with the positions already decided can always be finished.
+struct Bounds {
+    band: usize,
+    /// For each position and each of the `band` positions before it, along with the start of the bank,
+    /// the most batteries that can be turned on from the earlier position up to the later one.
+    most: Vec<i64>,
+    /// For the same pairs of positions, the negated fewest batteries that must be turned on between
+    /// them, so that both kinds of bound can be added together along a path of positions.
+    fewest: Vec<i64>,
+}
+
+impl Bounds {
+    /// The bounds for turning on exactly `batteries` batteries in a bank of `len` batteries, or `None`
+    /// if the constraints cannot be met at all.
+    fn new(len: usize, batteries: usize, constraints: &Constraints) -> Option<Self> {
+        let windows: Vec<Window> = constraints.no_adjacent.then_some(Window { limit: 1, width: 2 })
+            .into_iter()
+            .chain(constraints.window)
+            .collect();
+        let band = windows.iter()
+            .map(| window | window.width)
+            .chain(constraints.segment)
+            .fold(1, usize::max)
+            .min(len.max(1));
+        let mut bounds = Bounds { band, most: vec![UNBOUNDED; (len + 1) * (band + 1)], fewest: vec![UNBOUNDED; (len + 1) * (band + 1)] };
+
+        for position in 0..len {
+            bounds.limit(position, position + 1, 1);
+            bounds.limit(position + 1, position, 0);
+            for window in &windows {
+                bounds.limit(position, len.min(position + window.width), window.limit as i64);
+            }
+        }
+        if let Some(segment) = constraints.segment {
+            for start in (0..len).step_by(segment) {
+                bounds.limit(len.min(start + segment), start, -1);
+            }
+        }
+        bounds.limit(0, len, batteries as i64);
+        bounds.limit(len, 0, -(batteries as i64));
+
+        // Each bound through a later position also holds between the earlier positions on either
+        // side of it, so carry it over before moving back to them.
+        for position in (1..=len).rev() {
+            let earlier: Vec<usize> = bounds.earlier(position)
+                .filter(| &before | bounds.most[bounds.index(position, before)] < UNBOUNDED || bounds.fewest[bounds.index(position, before)] < UNBOUNDED)
+                .collect();
+            for &before in &earlier {
+                let index = bounds.index(position, before);
+                if bounds.most[index] + bounds.fewest[index] < 0 {
+                    return None;
+                }
+            }
+            for &from in &earlier {
+                for &to in &earlier {
+                    if from != to {
+                        let through = bounds.most[bounds.index(position, from)] + bounds.fewest[bounds.index(position, to)];
+                        bounds.limit(from, to, through);
+                    }
+                }
+            }
+        }
+
+        Some(bounds)
+    }
+
+    /// The start of the bank, and the positions within `band` before `position`.
+    fn earlier(&self, position: usize) -> impl Iterator<Item = usize> {
+        std::iter::once(0).chain(position.saturating_sub(self.band).max(1)..position)
+    }
+
+    fn index(&self, position: usize, before: usize) -> usize {
+        position * (self.band + 1) + if before == 0 { 0 } else { position - before }
+    }
+
+    /// Allows at most `most` more batteries to be turned on before `to` than before `from`.
+    fn limit(&mut self, from: usize, to: usize, most: i64) {
+        if from < to {
+            let index = self.index(to, from);
+            self.most[index] = self.most[index].min(most);
+        } else if from > to {
+            let index = self.index(from, to);
+            self.fewest[index] = self.fewest[index].min(most);
+        }
+    }
+
+    /// The fewest and most batteries that can be turned on before `position`, given how many are
+    /// turned on before each earlier one.
+    fn range(&self, position: usize, turned_on_before: impl Fn(usize) -> i64) -> (i64, i64) {
+        self.earlier(position).fold((i64::MIN, i64::MAX), | (fewest, most), before | {
+            let index = self.index(position, before);
+            let count = turned_on_before(before);
+            (fewest.max(count - self.fewest[index]), most.min(count + self.most[index]))
+        })
+    }
+}
+
+/// Turns on exactly `batteries` batteries in the bank to give the largest joltage that meets the
+/// constraints, or `None` if no choice of batteries meets them.
+///
+/// The joltage is built a digit at a time, from the largest battery that can still be turned on
+/// next. Of equal batteries in the same segment, the earliest can be followed by anything a later
+/// one can, so it is the only one kept. Equal batteries in different segments are each followed up,
+/// as turning on the later one may leave the earlier segment to be covered by one already on.
+pub fn select_batteries(bank: &[char], batteries: usize, constraints: &Constraints) -> Option<Selection> {
+    let bounds = Bounds::new(bank.len(), batteries, constraints)?;
+    let segment_of = | position: usize | constraints.segment.map_or(0, | segment | position / segment);
+
+    let mut choices: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut digits = String::with_capacity(batteries);
+    for _ in 0..batteries {
+        let mut next: Vec<(char, usize, usize)> = Vec::new();
+        for (choice, positions) in choices.iter().enumerate() {
+            let turned_on = positions.len() as i64;
+            let turned_on_before = | position: usize | positions.partition_point(| &p | p < position) as i64;
+
+            for (position, &battery) in bank.iter().enumerate().skip(positions.last().map_or(0, | &last | last + 1)) {
+                let (fewest, most) = bounds.range(position + 1, turned_on_before);
+                if (fewest..=most).contains(&(turned_on + 1)) {
+                    let earlier = next.iter().any(| &(digit, other, p) | other == choice && digit == battery && segment_of(p) == segment_of(position));
+                    if !earlier {
+                        next.push((battery, choice, position));
+                    }
+                    if battery == '9' && constraints.segment.is_none() {
+                        break;
+                    }
+                }
+                if !(fewest..=most).contains(&turned_on) {
+                    break;
+                }
+            }
+        }
+
+        let digit = next.iter().map(| &(digit, _, _) | digit).max()?;
+        let mut chosen: Vec<Vec<usize>> = next.into_iter()
+            .filter(| &(d, _, _) | d == digit)
+            .map(| (_, choice, position) | {
+                let mut positions = choices[choice].clone();
+                positions.push(position);
+                positions
+            })
+            .collect();
+
+        // Choices that agree on the batteries close enough to matter have the same future, so only the
+        // earliest of them is kept.
+        chosen.sort();
+        let mut seen = HashSet::new();
+        chosen.retain(| positions | {
+            let last = positions[positions.len() - 1];
+            let recent: Vec<usize> = positions.iter().copied().filter(| &p | p + bounds.band > last).collect();
+            seen.insert(recent)
+        });
+
+        choices = chosen;
+        digits.push(digit);
+    }
+
+    let positions = choices.into_iter().next()?;
+    Some(Selection { digits, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether turning on the batteries at `positions` in a bank of `len` batteries meets the
+    /// constraints, checked directly from their definitions.
+    fn allows(constraints: &Constraints, positions: &[usize], len: usize) -> bool {
+        let no_adjacent = !constraints.no_adjacent || positions.windows(2).all(| pair | pair[1] > pair[0] + 1);
+        let segments = constraints.segment.is_none_or(| segment | {
+            (0..len).step_by(segment).all(| start | positions.iter().any(| &p | p >= start && p < start + segment))
+        });
+        let window = constraints.window.is_none_or(| window | {
+            (0..len).all(| start | positions.iter().filter(| &&p | p >= start && p < start + window.width).count() <= window.limit)
+        });
+        no_adjacent && segments && window
+    }
+
+    /// The largest joltage that meets the constraints for each number of batteries, found by trying
+    /// every choice of batteries.
+    fn exhaustive_joltages(bank: &[char], constraints: &Constraints) -> Vec<Option<String>> {
+        let mut joltages = vec![None; bank.len() + 1];
+        for mask in 0u32..1 << bank.len() {
+            let positions: Vec<usize> = (0..bank.len()).filter(| i | mask & (1 << i) != 0).collect();
+            if allows(constraints, &positions, bank.len()) {
+                let joltage = Some(positions.iter().map(| &i | bank[i]).collect::<String>());
+                joltages[positions.len()] = joltages[positions.len()].take().max(joltage);
+            }
+        }
+        joltages
+    }
+
+    #[test]
+    fn constrained_batteries_can_be_selected() {
+        let bank: Vec<char> = "818181911112111".chars().collect();
+
+        let unconstrained = select_batteries(&bank, 2, &Constraints::default()).unwrap();
+        assert_eq!(unconstrained, Selection { digits: "92".to_string(), positions: vec![6, 11] });
+
+        let no_adjacent = Constraints { no_adjacent: true, ..Constraints::default() };
+        assert_eq!(select_batteries(&bank, 6, &no_adjacent), Some(Selection { digits: "891111".to_string(), positions: vec![0, 6, 8, 10, 12, 14] }));
+        assert_eq!(select_batteries(&bank, 9, &no_adjacent), None);
+
+        let segments = Constraints { segment: Some(5), ..Constraints::default() };
+        assert_eq!(select_batteries(&bank, 2, &segments), None);
+        assert_eq!(select_batteries(&bank, 3, &segments), Some(Selection { digits: "892".to_string(), positions: vec![0, 6, 11] }));
+
+        // With equal batteries, the earliest ones that meet the constraints are turned on.
+        let bank: Vec<char> = "99999".chars().collect();
+        let window = Constraints { window: Some(Window { limit: 2, width: 3 }), ..Constraints::default() };
+        assert_eq!(select_batteries(&bank, 3, &window), Some(Selection { digits: "999".to_string(), positions: vec![0, 1, 3] }));
+        assert_eq!(select_batteries(&bank, 5, &window), None);
+    }
+
+    #[test]
+    fn constrained_selections_match_an_exhaustive_search() {
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = | bound: u64 | {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % bound
+        };
+
+        let mut rule_sets = Vec::new();
+        for no_adjacent in [false, true] {
+            for segment in [None, Some(1), Some(2), Some(3), Some(4)] {
+                for window in [None, Some(Window { limit: 1, width: 3 }), Some(Window { limit: 2, width: 3 }), Some(Window { limit: 2, width: 5 })] {
+                    rule_sets.push(Constraints { no_adjacent, segment, window });
+                }
+            }
+        }
+
+        for _ in 0..60 {
+            let len = 1 + next(11) as usize;
+            // Few distinct digits make for many ties between batteries.
+            let bank: Vec<char> = (0..len).map(| _ | char::from_digit(1 + 2 * next(4) as u32, 10).unwrap()).collect();
+
+            for constraints in &rule_sets {
+                let exhaustive = exhaustive_joltages(&bank, constraints);
+                for (batteries, expected) in exhaustive.iter().enumerate().skip(1) {
+                    let selection = select_batteries(&bank, batteries, constraints);
+                    let context = format!("{} {} {:?}", bank.iter().collect::<String>(), batteries, constraints);
+                    assert_eq!(selection.as_ref().map(| selection | &selection.digits), expected.as_ref(), "{}", context);
+
+                    if let Some(selection) = selection {
+                        assert!(allows(constraints, &selection.positions, len), "{}", context);
+                        assert_eq!(selection.positions.iter().map(| &i | bank[i]).collect::<String>(), selection.digits, "{}", context);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_banks_can_be_searched() {
+        let no_adjacent = Constraints { no_adjacent: true, ..Constraints::default() };
+
+        let bank: Vec<char> = "12".repeat(150_000).chars().collect();
+        assert_eq!(select_batteries(&bank, 2, &no_adjacent), Some(Selection { digits: "22".to_string(), positions: vec![1, 3] }));
+
+        let bank: Vec<char> = "19".repeat(10_000).chars().collect();
+        let selection = select_batteries(&bank, 300, &no_adjacent).unwrap();
+        assert_eq!(selection.digits, "9".repeat(300));
+        assert_eq!(selection.positions, (0..300).map(| i | 2 * i + 1).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn windows_can_be_parsed() {
+        assert_eq!(Window::from_str("3/10"), Ok(Window { limit: 3, width: 10 }));
+        assert!(Window::from_str("0/10").is_err());
+        assert!(Window::from_str("3").is_err());
+        assert!(Window::from_str("3/x").is_err());
+    }
+}
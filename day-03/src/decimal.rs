@@ -0,0 +1,138 @@
+use std::fmt;
+use std::ops::{Add, AddAssign};
+
+/// Each limb holds nine decimal digits.
+const LIMB_BASE: u32 = 1_000_000_000;
+
+/// A non-negative decimal number of any size, for joltages and totals that do not fit in a `u128`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Decimal {
+    /// The limbs of the number, least significant first, with no trailing zero limbs, so that zero
+    /// has none at all.
+    limbs: Vec<u32>,
+}
+
+impl Decimal {
+    /// Appends a digit to the end of the number, multiplying it by ten first.
+    pub fn append_digit(&mut self, digit: u32) {
+        assert!(digit < 10);
+
+        let mut carry = digit as u64;
+        for limb in self.limbs.iter_mut() {
+            let value = *limb as u64 * 10 + carry;
+            *limb = (value % LIMB_BASE as u64) as u32;
+            carry = value / LIMB_BASE as u64;
+        }
+
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+}
+
+impl From<u128> for Decimal {
+    fn from(mut value: u128) -> Self {
+        let mut limbs = Vec::new();
+        while value > 0 {
+            limbs.push((value % LIMB_BASE as u128) as u32);
+            value /= LIMB_BASE as u128;
+        }
+
+        Decimal { limbs }
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(value: u64) -> Self {
+        Decimal::from(value as u128)
+    }
+}
+
+impl AddAssign<&Decimal> for Decimal {
+    fn add_assign(&mut self, other: &Decimal) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+
+        let mut carry = 0;
+        for (i, limb) in self.limbs.iter_mut().enumerate() {
+            let value = *limb + other.limbs.get(i).copied().unwrap_or(0) + carry;
+            *limb = value % LIMB_BASE;
+            carry = value / LIMB_BASE;
+        }
+
+        if carry > 0 {
+            self.limbs.push(carry);
+        }
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, other: Decimal) {
+        *self += &other;
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(mut self, other: Decimal) -> Decimal {
+        self += &other;
+        self
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((most_significant, rest)) = self.limbs.split_last() else {
+            return write!(f, "0");
+        };
+
+        write!(f, "{}", most_significant)?;
+        for limb in rest.iter().rev() {
+            write!(f, "{:09}", limb)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(digits: &str) -> Decimal {
+        let mut decimal = Decimal::default();
+        for c in digits.chars() {
+            decimal.append_digit(c.to_digit(10).unwrap());
+        }
+        decimal
+    }
+
+    #[test]
+    fn decimals_can_be_built_and_displayed() {
+        assert_eq!(Decimal::default().to_string(), "0");
+        assert_eq!(decimal("000").to_string(), "0");
+        assert_eq!(decimal("0001000000000").to_string(), "1000000000");
+        assert_eq!(Decimal::from(u128::MAX).to_string(), u128::MAX.to_string());
+        assert_eq!(Decimal::from(u128::MAX), decimal(&u128::MAX.to_string()));
+        assert_eq!(Decimal::from(0u64), Decimal::default());
+
+        let digits = "9876543210".repeat(30);
+        assert_eq!(decimal(&digits).to_string(), digits);
+    }
+
+    #[test]
+    fn decimals_can_be_added() {
+        assert_eq!(Decimal::from(999_999_999u64) + Decimal::from(1u64), Decimal::from(1_000_000_000u64));
+        assert_eq!(Decimal::from(u64::MAX) + Decimal::from(u64::MAX), Decimal::from(2 * u64::MAX as u128));
+        assert_eq!(Decimal::default() + Decimal::from(42u64), Decimal::from(42u64));
+
+        let mut total = Decimal::from(u128::MAX);
+        total += Decimal::from(1u64);
+        assert_eq!(total.to_string(), "340282366920938463463374607431768211456");
+
+        total += &decimal(&"9".repeat(50));
+        assert_eq!(total.to_string(), "100000000000340282366920938463463374607431768211455");
+    }
+}
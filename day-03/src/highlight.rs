@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+const RESET: &str = "\x1b[0m";
+const SELECTED_STYLE: &str = "\x1b[1;32m";
+
+/// How to pick out the batteries that are turned on when showing a bank.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighlightStyle {
+    /// Wraps each run of selected batteries in square brackets, such as `[9]1111[2111]`.
+    Brackets,
+    /// Shows the selected batteries in bold green on an ANSI terminal.
+    Ansi,
+}
+
+impl FromStr for HighlightStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "brackets" => Ok(HighlightStyle::Brackets),
+            "ansi" => Ok(HighlightStyle::Ansi),
+            _ => Err(format!("Unknown highlight style {:?}, expected \"brackets\" or \"ansi\".", s)),
+        }
+    }
+}
+
+/// The bank with the batteries at `positions`, which must be in ascending order, highlighted.
+pub fn highlight(bank: &str, positions: &[usize], style: HighlightStyle) -> String {
+    let (start, end) = match style {
+        HighlightStyle::Brackets => ("[", "]"),
+        HighlightStyle::Ansi => (SELECTED_STYLE, RESET),
+    };
+
+    let mut highlighted = String::with_capacity(bank.len() + 2 * positions.len());
+    let mut positions = positions.iter().peekable();
+    let mut selecting = false;
+    for (i, c) in bank.chars().enumerate() {
+        let selected = positions.next_if_eq(&&i).is_some();
+        if selected != selecting {
+            highlighted.push_str(if selected { start } else { end });
+            selecting = selected;
+        }
+        highlighted.push(c);
+    }
+
+    if selecting {
+        highlighted.push_str(end);
+    }
+
+    highlighted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selected_batteries_can_be_bracketed() {
+        assert_eq!(highlight("818181911112111", &[0, 2, 4, 6, 11, 12, 13, 14], HighlightStyle::Brackets), "[8]1[8]1[8]1[9]1111[2111]");
+        assert_eq!(highlight("987654321111111", &[0, 1], HighlightStyle::Brackets), "[98]7654321111111");
+        assert_eq!(highlight("1234", &[], HighlightStyle::Brackets), "1234");
+    }
+
+    #[test]
+    fn selected_batteries_can_be_coloured() {
+        let highlighted = highlight("811111111111119", &[0, 14], HighlightStyle::Ansi);
+        assert_eq!(highlighted, format!("{}8{}1111111111111{}9{}", SELECTED_STYLE, RESET, SELECTED_STYLE, RESET));
+    }
+}
@@ -1,86 +1,301 @@
+mod constraints;
+mod decimal;
+mod highlight;
+mod validation;
+
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::str::FromStr;
+
+use decimal::Decimal;
+use highlight::HighlightStyle;
+use constraints::{Constraints, Window};
+use validation::{BankPolicy, UnsatisfiableBank};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let options = match Options::parse(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
 
-    let file_path = &args[1];
-    let path = Path::new(file_path);
-    let file = File::open(path);
+    let file = File::open(&options.file_path);
 
     if file.is_err() {
-        println!("Unable to read the file {:?}", file_path);
+        println!("Unable to read the file {:?}", options.file_path);
         return;
     }
 
     let reader = BufReader::new(file.unwrap());
-    let mut total_output_simple_joltage = 0;
-    let mut total_output_complex_joltage = 0u64;
-
-    for line in reader.lines() {
-        match line {
-            Ok(l) => {
-                // Strip BOM if present
-                let clean = l.trim_start_matches('\u{feff}');
-                total_output_simple_joltage = total_output_simple_joltage + clean.to_string().calculate_simple_joltage();
-                total_output_complex_joltage = total_output_complex_joltage + clean.to_string().calculate_complex_joltage();
+    let batteries = options.batteries.map_or(vec![2, 12], | batteries | vec![batteries]);
+    let mut total_output_joltages = vec![Decimal::default(); batteries.len()];
+    let mut invalid_banks = 0;
+    let mut banks_left_out = vec![0; batteries.len()];
+
+    for (index, line) in reader.split(b'\n').enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                println!("Unable to read the file {:?}: {}", options.file_path, error);
+                return;
+            },
+        };
+
+        let clean = match validation::validate_bank(index + 1, &line) {
+            Ok(clean) => clean,
+            Err(invalid_bank) => {
+                match options.invalid_banks {
+                    BankPolicy::Fail => {
+                        println!("Invalid bank at {}.", invalid_bank);
+                        return;
+                    },
+                    BankPolicy::Report => println!("Skipping the invalid bank at {}.", invalid_bank),
+                    BankPolicy::Skip => {},
+                }
+                invalid_banks += 1;
+                continue;
             },
-            Err(_) => continue,
+        };
+
+        // Blank lines, such as one at the end of the file, separate banks rather than being empty ones.
+        if clean.is_empty() {
+            continue;
+        }
+
+        // Each total is worked out separately, so a bank with too few batteries, or none that meet the
+        // constraints, only leaves it out of the totals it cannot be part of.
+        for ((total_output_joltage, banks_left_out), &batteries) in total_output_joltages.iter_mut().zip(&mut banks_left_out).zip(&batteries) {
+            let joltage = validation::check_battery_count(index + 1, clean, batteries)
+                .map_err(| invalid_bank | invalid_bank.to_string())
+                .and_then(|()| bank_joltage(clean, index + 1, batteries, &options).map_err(| unsatisfiable_bank | unsatisfiable_bank.to_string()));
+
+            match joltage {
+                Ok(joltage) => *total_output_joltage += joltage,
+                Err(reason) => {
+                    match options.invalid_banks {
+                        BankPolicy::Fail => {
+                            println!("Invalid bank at {}.", reason);
+                            return;
+                        },
+                        BankPolicy::Report => println!("Skipping {} batteries for the bank at {}.", batteries, reason),
+                        BankPolicy::Skip => {},
+                    }
+                    *banks_left_out += 1;
+                },
+            }
         }
     }
 
-    println!("The total output simple joltage is {}.", total_output_simple_joltage);
-    println!("The total output complex joltage is {}.", total_output_complex_joltage);
+    match options.batteries {
+        Some(batteries) => println!("The total output joltage with {} batteries is {}.", batteries, total_output_joltages[0]),
+        None => {
+            println!("The total output simple joltage is {}.", total_output_joltages[0]);
+            println!("The total output complex joltage is {}.", total_output_joltages[1]);
+        },
+    }
+
+    if options.invalid_banks == BankPolicy::Report {
+        if invalid_banks > 0 {
+            println!("Invalid banks left out of the totals: {}.", invalid_banks);
+        }
+        for (batteries, &banks_left_out) in batteries.iter().zip(&banks_left_out) {
+            if banks_left_out > 0 {
+                println!("Banks left out of the total with {} batteries: {}.", batteries, banks_left_out);
+            }
+        }
+    }
+}
+
+/// The joltage from turning on `batteries` batteries in the valid bank on `line`, which has at least
+/// that many, showing which ones are turned on if asked.
+fn bank_joltage(bank: &str, line: usize, batteries: usize, options: &Options) -> Result<Decimal, UnsatisfiableBank> {
+    let bank = bank.to_string();
+
+    // Without constraints, a bank with enough batteries always has a joltage, so it is calculated directly.
+    if options.constraints == Constraints::default() {
+        if let Some(style) = options.highlight {
+            let selection = bank.select_batteries(batteries);
+            println!("{}  {}", highlight::highlight(&bank, &selection.positions, style), selection.digits);
+        }
+
+        // A decimal joltage always fits, however many batteries are turned on.
+        return Ok(match (options.batteries, batteries) {
+            (None, 2) => Decimal::from(bank.calculate_simple_joltage() as u64),
+            (None, 12) => Decimal::from(bank.calculate_complex_joltage()),
+            _ => bank.calculate_joltage::<Decimal>(batteries).unwrap(),
+        });
+    }
+
+    let selection = bank.select_batteries_under(batteries, &options.constraints).ok_or(UnsatisfiableBank { line, batteries })?;
+    if let Some(style) = options.highlight {
+        println!("{}  {}", highlight::highlight(&bank, &selection.positions, style), selection.digits);
+    }
+
+    Ok(selection.joltage::<Decimal>().unwrap())
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    file_path: String,
+    /// How many batteries to turn on in each bank, or `None` for both the simple and complex joltages.
+    batteries: Option<usize>,
+    /// Show each bank with the batteries turned on highlighted in this style, along with its joltage.
+    highlight: Option<HighlightStyle>,
+    /// What to do with banks that are not all batteries, have too few of them, or cannot meet the constraints.
+    invalid_banks: BankPolicy,
+    /// The safety rules on which batteries may be turned on together.
+    constraints: Constraints,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut file_path = None;
+        let mut batteries = None;
+        let mut highlight = None;
+        let mut invalid_banks = BankPolicy::Fail;
+        let mut constraints = Constraints::default();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--batteries" => {
+                    let value = Self::required_value(arg, args.next())?;
+                    batteries = Some(value.parse::<usize>().ok()
+                        .filter(| &batteries | batteries > 0)
+                        .ok_or_else(|| format!("The option {} expects a whole number of at least 1, not {:?}.", arg, value))?);
+                },
+                "--highlight" => highlight = Some(HighlightStyle::from_str(Self::required_value(arg, args.next())?)?),
+                "--invalid-banks" => invalid_banks = BankPolicy::from_str(Self::required_value(arg, args.next())?)?,
+                "--no-adjacent" => constraints.no_adjacent = true,
+                "--segment" => {
+                    let value = Self::required_value(arg, args.next())?;
+                    constraints.segment = Some(value.parse::<usize>().ok()
+                        .filter(| &segment | segment > 0)
+                        .ok_or_else(|| format!("The option {} expects a whole number of at least 1, not {:?}.", arg, value))?);
+                },
+                "--window" => constraints.window = Some(Window::from_str(Self::required_value(arg, args.next())?)?),
+                flag if flag.starts_with("--") => return Err(format!("Unknown option {:?}.", flag)),
+                path if file_path.is_none() => file_path = Some(path.to_string()),
+                extra => return Err(format!("Unexpected argument {:?}.", extra)),
+            }
+        }
+
+        let file_path = file_path.ok_or_else(|| "Expected the path of a file of battery banks.".to_string())?;
+        Ok(Self { file_path, batteries, highlight, invalid_banks, constraints })
+    }
+
+    fn required_value<'a>(flag: &str, value: Option<&'a String>) -> Result<&'a String, String> {
+        value.ok_or_else(|| format!("The option {} requires a value.", flag))
+    }
 }
 
 trait JoltageCalculator {
     fn calculate_simple_joltage(&self) -> i32;
 
     fn calculate_complex_joltage(&self) -> u64;
+
+    /// The joltage from turning on `batteries` batteries, or `None` if it does not fit in `J`.
+    fn calculate_joltage<J: Joltage>(&self, batteries: usize) -> Option<J>;
+
+    /// Turns on exactly `batteries` batteries in the bank to give the largest joltage, which is the
+    /// largest number made of that many of its digits, kept in order.
+    fn select_batteries(&self, batteries: usize) -> Selection;
+
+    /// Turns on exactly `batteries` batteries in the bank to give the largest joltage that meets the
+    /// constraints, or returns `None` if no choice of batteries meets them.
+    fn select_batteries_under(&self, batteries: usize, constraints: &Constraints) -> Option<Selection>;
 }
 
-impl JoltageCalculator for String {
-    fn calculate_simple_joltage(&self) -> i32 {
-        let chars: Vec<char> = self.chars().collect();
-        let mut maximum_joltage = 0;
+/// A number type that a joltage can be built up in, one digit at a time.
+trait Joltage: Default {
+    /// Appends a digit to the joltage, or returns `None` if the result does not fit.
+    fn append_digit(self, digit: u32) -> Option<Self>;
+}
 
-        for i in 0..chars.len() - 1 {
-            for j in (i+1)..chars.len() {
-                let joltage = chars[i].to_digit(10).unwrap() * 10 + chars[j].to_digit(10).unwrap();
-                maximum_joltage = maximum_joltage.max(joltage as i32);
+macro_rules! impl_joltage {
+    ($($t:ty),*) => {
+        $(
+            impl Joltage for $t {
+                fn append_digit(self, digit: u32) -> Option<Self> {
+                    self.checked_mul(10)?.checked_add(digit as $t)
+                }
             }
-        }
+        )*
+    };
+}
+
+impl_joltage!(u64, u128);
+
+impl Joltage for Decimal {
+    fn append_digit(mut self, digit: u32) -> Option<Self> {
+        Decimal::append_digit(&mut self, digit);
+        Some(self)
+    }
+}
+
+/// The batteries turned on in a bank, and the digits of the joltage they give.
+#[derive(Debug, PartialEq)]
+struct Selection {
+    digits: String,
+    /// The positions of the batteries turned on, in ascending order.
+    positions: Vec<usize>,
+}
+
+impl Selection {
+    /// The joltage of the selection, or `None` if it does not fit in `J`.
+    fn joltage<J: Joltage>(&self) -> Option<J> {
+        self.digits.chars().try_fold(J::default(), | joltage, c | joltage.append_digit(c.to_digit(10).unwrap()))
+    }
+}
 
-        maximum_joltage
+impl JoltageCalculator for String {
+    // Two and twelve digits always fit in a `u64`.
+    fn calculate_simple_joltage(&self) -> i32 {
+        self.calculate_joltage::<u64>(2).unwrap() as i32
     }
 
     fn calculate_complex_joltage(&self) -> u64 {
-        let chars: Vec<char> = self.chars().collect();
-        let mut result = String::new();
-        let mut start = 0;
+        self.calculate_joltage(12).unwrap()
+    }
 
-        for position in 0..12 {
-            let remaining_needed = 12 - position - 1;
+    fn calculate_joltage<J: Joltage>(&self, batteries: usize) -> Option<J> {
+        self.select_batteries(batteries).joltage()
+    }
 
-            let search_end = chars.len() - remaining_needed;
+    fn select_batteries(&self, batteries: usize) -> Selection {
+        let chars: Vec<char> = self.chars().collect();
+        let mut positions: Vec<usize> = Vec::with_capacity(batteries);
 
-            let mut max_digit = '0';
-            let mut max_index = start;
+        // A digit is dropped for any larger digit after it, as long as enough digits are left to
+        // still turn on every battery. Each digit is pushed and popped at most once.
+        for (i, &c) in chars.iter().enumerate() {
+            while let Some(&last) = positions.last()
+                && chars[last] < c
+                && positions.len() - 1 + chars.len() - i >= batteries {
+                positions.pop();
+            }
 
-            for i in start..search_end {
-                if chars[i] > max_digit {
-                    max_digit = chars[i];
-                    max_index = i;
-                }
+            if positions.len() < batteries {
+                positions.push(i);
             }
+        }
+
+        let digits = positions.iter().map(| &i | chars[i]).collect();
+        Selection { digits, positions }
+    }
 
-            result.push(max_digit);
-            start = max_index + 1;
+    fn select_batteries_under(&self, batteries: usize, constraints: &Constraints) -> Option<Selection> {
+        // Without constraints, the linear selection gives the same batteries as the search.
+        if *constraints == Constraints::default() {
+            return Some(self.select_batteries(batteries));
         }
 
-        result.parse::<u64>().unwrap()
+        let chars: Vec<char> = self.chars().collect();
+        constraints::select_batteries(&chars, batteries, constraints)
     }
 }
 
@@ -117,4 +332,76 @@ mod tests {
         let bank = "818181911112111".to_owned();
         assert_eq!(888911112111u64, bank.calculate_complex_joltage());
     }
-}
\ No newline at end of file
+
+    fn exhaustive_joltage(bank: &str, batteries: usize) -> u64 {
+        let digits: Vec<u64> = bank.chars().map(| c | c.to_digit(10).unwrap() as u64).collect();
+        (0u32..1 << digits.len())
+            .filter(| mask | mask.count_ones() as usize == batteries)
+            .map(| mask | (0..digits.len()).filter(| i | mask & (1 << i) != 0).fold(0, | joltage, i | joltage * 10 + digits[i]))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn joltage_can_be_calculated_for_any_number_of_batteries() {
+        let bank = "818181911112111".to_owned();
+        assert_eq!(Some(9u64), bank.calculate_joltage(1));
+        assert_eq!(Some(92u64), bank.calculate_joltage(2));
+        assert_eq!(Some(912111u64), bank.calculate_joltage(6));
+        assert_eq!(Some(818181911112111u64), bank.calculate_joltage(15));
+
+        for bank in ["987654321111111", "811111111111119", "234234234234278", "818181911112111", "1213149", "5555", "1"] {
+            for batteries in 1..=bank.len() {
+                assert_eq!(Some(exhaustive_joltage(bank, batteries)), bank.to_string().calculate_joltage(batteries), "{} {}", bank, batteries);
+            }
+        }
+    }
+
+    #[test]
+    fn selected_batteries_can_be_found() {
+        let bank = "818181911112111".to_owned();
+        assert_eq!(bank.select_batteries(2), Selection { digits: "92".to_string(), positions: vec![6, 11] });
+        assert_eq!(bank.select_batteries(12), Selection { digits: "888911112111".to_string(), positions: vec![0, 2, 4, 6, 7, 8, 9, 10, 11, 12, 13, 14] });
+
+        let bank = "234234234234278".to_owned();
+        assert_eq!(bank.select_batteries(12).positions, vec![2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+
+        // With equal digits, the earliest batteries are turned on.
+        assert_eq!("5555".to_string().select_batteries(2).positions, vec![0, 1]);
+    }
+
+    #[test]
+    fn long_joltages_can_be_calculated() {
+        let bank = "9".repeat(20);
+        assert_eq!(None, bank.calculate_joltage::<u64>(20));
+        assert_eq!(Some(99_999_999_999_999_999_999u128), bank.calculate_joltage(20));
+        assert_eq!(None, "9".repeat(40).calculate_joltage::<u128>(39));
+
+        let bank = "123456789".repeat(50);
+        let joltage = bank.calculate_joltage::<Decimal>(300).unwrap();
+        assert_eq!(joltage.to_string(), "9".repeat(18) + "789" + &"123456789".repeat(31));
+    }
+
+    #[test]
+    fn options_can_be_parsed() {
+        let args = | s: &str | s.split_whitespace().map(String::from).collect::<Vec<String>>();
+
+        assert_eq!(Options::parse(&args("data/puzzle_input.txt")), Ok(Options { file_path: "data/puzzle_input.txt".to_string(), batteries: None, highlight: None, invalid_banks: BankPolicy::Fail, constraints: Constraints::default() }));
+        assert_eq!(
+            Options::parse(&args("--batteries 3 data/puzzle_input.txt --highlight ansi")),
+            Ok(Options { file_path: "data/puzzle_input.txt".to_string(), batteries: Some(3), highlight: Some(HighlightStyle::Ansi), invalid_banks: BankPolicy::Fail, constraints: Constraints::default() })
+        );
+        assert_eq!(Options::parse(&args("--invalid-banks report data/puzzle_input.txt")).map(| options | options.invalid_banks), Ok(BankPolicy::Report));
+        assert!(Options::parse(&args("--invalid-banks ignore data/puzzle_input.txt")).is_err());
+        assert_eq!(
+            Options::parse(&args("--no-adjacent --segment 10 --window 3/8 data/puzzle_input.txt")).map(| options | options.constraints),
+            Ok(Constraints { no_adjacent: true, segment: Some(10), window: Some(Window { limit: 3, width: 8 }) })
+        );
+        assert!(Options::parse(&args("--segment 0 data/puzzle_input.txt")).is_err());
+        assert!(Options::parse(&args("--window 3 data/puzzle_input.txt")).is_err());
+        assert!(Options::parse(&args("--highlight bold data/puzzle_input.txt")).is_err());
+        assert!(Options::parse(&args("--batteries 0 data/puzzle_input.txt")).is_err());
+        assert!(Options::parse(&args("--batteries")).is_err());
+        assert!(Options::parse(&args("")).is_err());
+    }
+}
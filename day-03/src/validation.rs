@@ -0,0 +1,145 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do with a bank that cannot be turned on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BankPolicy {
+    /// Leaves invalid banks out of the totals without saying so.
+    Skip,
+    /// Stops at the first invalid bank without giving any totals.
+    Fail,
+    /// Leaves invalid banks out of the totals, and says why each one was left out.
+    Report,
+}
+
+impl FromStr for BankPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(BankPolicy::Skip),
+            "fail" => Ok(BankPolicy::Fail),
+            "report" => Ok(BankPolicy::Report),
+            _ => Err(format!("Unknown invalid bank policy {:?}, expected \"skip\", \"fail\" or \"report\".", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BankError {
+    /// The line is not UTF-8 text from this column on.
+    NotUtf8,
+    /// The character is not a battery's joltage rating.
+    InvalidBattery(char),
+    /// The bank has fewer batteries than need to be turned on.
+    TooFewBatteries { found: usize, needed: usize },
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BankError::NotUtf8 => write!(f, "the line is not UTF-8 text"),
+            BankError::InvalidBattery(c) => write!(f, "{:?} is not a battery", c),
+            BankError::TooFewBatteries { found, needed } => write!(f, "the bank has {} batteries, but {} need to be turned on", found, needed),
+        }
+    }
+}
+
+/// A bank that cannot be turned on, along with where the problem is. A bank with too few batteries
+/// is reported at the column just after its last battery.
+#[derive(Debug, PartialEq)]
+pub struct InvalidBank {
+    pub line: usize,
+    pub column: usize,
+    pub reason: BankError,
+}
+
+impl fmt::Display for InvalidBank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.reason)
+    }
+}
+
+/// A valid bank where no choice of `batteries` batteries meets the constraints. This is down to the
+/// bank as a whole rather than any one battery in it, so it has no column, and other numbers of
+/// batteries may still be turned on.
+#[derive(Debug, PartialEq)]
+pub struct UnsatisfiableBank {
+    pub line: usize,
+    pub batteries: usize,
+}
+
+impl fmt::Display for UnsatisfiableBank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: no {} batteries can be turned on together within the constraints", self.line, self.batteries)
+    }
+}
+
+/// Reads the bank on `line` from its bytes, without any byte order mark or carriage return, and
+/// checks that every character is a battery. A blank line gives an empty bank.
+pub fn validate_bank(line: usize, bytes: &[u8]) -> Result<&str, InvalidBank> {
+    let text = std::str::from_utf8(bytes).map_err(| error | {
+        // Everything before the error is valid, so its characters can be counted for the column.
+        let valid = std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap();
+        InvalidBank { line, column: valid.trim_start_matches('\u{feff}').chars().count() + 1, reason: BankError::NotUtf8 }
+    })?;
+
+    let bank = text.trim_start_matches('\u{feff}').trim_end_matches('\r');
+    for (i, c) in bank.chars().enumerate() {
+        if !c.is_ascii_digit() {
+            return Err(InvalidBank { line, column: i + 1, reason: BankError::InvalidBattery(c) });
+        }
+    }
+
+    Ok(bank)
+}
+
+/// Checks that a valid bank on `line` has enough batteries to turn on `batteries` of them.
+pub fn check_battery_count(line: usize, bank: &str, batteries: usize) -> Result<(), InvalidBank> {
+    // Every battery is a single ASCII digit, so the bank's length is its number of batteries.
+    if bank.len() < batteries {
+        return Err(InvalidBank { line, column: bank.len() + 1, reason: BankError::TooFewBatteries { found: bank.len(), needed: batteries } });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banks_can_be_validated() {
+        assert_eq!(validate_bank(1, b"987654321111111"), Ok("987654321111111"));
+        assert_eq!(validate_bank(1, b"\xef\xbb\xbf98\r"), Ok("98"));
+        assert_eq!(validate_bank(1, b""), Ok(""));
+        assert_eq!(validate_bank(3, b"8181x1911112111"), Err(InvalidBank { line: 3, column: 5, reason: BankError::InvalidBattery('x') }));
+        assert_eq!(validate_bank(2, b"9876 54"), Err(InvalidBank { line: 2, column: 5, reason: BankError::InvalidBattery(' ') }));
+        assert_eq!(validate_bank(1, "1é".as_bytes()), Err(InvalidBank { line: 1, column: 2, reason: BankError::InvalidBattery('é') }));
+
+        // A line that is not UTF-8 is reported at its first byte that is not, rather than dropped.
+        assert_eq!(validate_bank(2, b"81111\xff1111111119"), Err(InvalidBank { line: 2, column: 6, reason: BankError::NotUtf8 }));
+        assert_eq!(validate_bank(1, b"\xef\xbb\xbf9\xc3"), Err(InvalidBank { line: 1, column: 2, reason: BankError::NotUtf8 }));
+
+        assert_eq!(check_battery_count(4, "12345", 2), Ok(()));
+        assert_eq!(check_battery_count(4, "12345", 12), Err(InvalidBank { line: 4, column: 6, reason: BankError::TooFewBatteries { found: 5, needed: 12 } }));
+
+        let invalid_bank = InvalidBank { line: 3, column: 5, reason: BankError::InvalidBattery('x') };
+        assert_eq!(invalid_bank.to_string(), "line 3, column 5: 'x' is not a battery");
+        let invalid_bank = InvalidBank { line: 4, column: 6, reason: BankError::TooFewBatteries { found: 5, needed: 12 } };
+        assert_eq!(invalid_bank.to_string(), "line 4, column 6: the bank has 5 batteries, but 12 need to be turned on");
+        let invalid_bank = InvalidBank { line: 2, column: 6, reason: BankError::NotUtf8 };
+        assert_eq!(invalid_bank.to_string(), "line 2, column 6: the line is not UTF-8 text");
+
+        let unsatisfiable_bank = UnsatisfiableBank { line: 1, batteries: 2 };
+        assert_eq!(unsatisfiable_bank.to_string(), "line 1: no 2 batteries can be turned on together within the constraints");
+    }
+
+    #[test]
+    fn policies_can_be_parsed() {
+        assert_eq!(BankPolicy::from_str("skip"), Ok(BankPolicy::Skip));
+        assert_eq!(BankPolicy::from_str("fail"), Ok(BankPolicy::Fail));
+        assert_eq!(BankPolicy::from_str("report"), Ok(BankPolicy::Report));
+        assert!(BankPolicy::from_str("ignore").is_err());
+    }
+}